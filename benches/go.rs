@@ -1,4 +1,3 @@
-use chess;
 use criterion::{black_box, criterion_group, criterion_main, Criterion};
 
 fn go(depth: u8) -> Result<(), chess::ChessError> {
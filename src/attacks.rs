@@ -0,0 +1,475 @@
+//! Precomputed attack tables.
+//!
+//! Sliding pieces use "fancy" magic bitboards; learn more about them here:
+//! https://www.chessprogramming.org/Magic_Bitboards
+
+use crate::{Bitboard, Color, Coordinate, BOARD_HEIGHT, BOARD_WIDTH};
+use std::sync::OnceLock;
+
+const SQUARES: usize = (BOARD_WIDTH * BOARD_HEIGHT) as usize;
+
+const ROOK_DIRECTIONS: [(i8, i8); 4] = [(0, 1), (1, 0), (0, -1), (-1, 0)];
+const BISHOP_DIRECTIONS: [(i8, i8); 4] = [(1, 1), (1, -1), (-1, -1), (-1, 1)];
+const KNIGHT_OFFSETS: [(i8, i8); 8] = [
+    (1, 2),
+    (2, 1),
+    (2, -1),
+    (1, -2),
+    (-1, -2),
+    (-2, -1),
+    (-2, 1),
+    (-1, 2),
+];
+const KING_OFFSETS: [(i8, i8); 8] = [
+    (0, 1),
+    (1, 1),
+    (1, 0),
+    (1, -1),
+    (0, -1),
+    (-1, -1),
+    (-1, 0),
+    (-1, 1),
+];
+
+// The magic numbers were found by trial and error with a fixed seed; any number that maps every
+// relevant occupancy to a distinct (or constructively colliding) index works.
+const ROOK_MAGICS: [u64; 64] = [
+    0x0080_0080_4000_2018,
+    0x0040_1000_4000_2001,
+    0x0900_0D00_1020_0040,
+    0x8080_0800_1000_8004,
+    0xA280_0800_0234_0080,
+    0x2500_0500_2400_0208,
+    0x0280_0100_0080_0200,
+    0x1100_1443_8022_0100,
+    0xA920_8004_8C20_4002,
+    0x0802_8040_0320_0080,
+    0x0108_8020_0010_0089,
+    0x8060_8008_0080_1002,
+    0xA002_0008_2200_0410,
+    0x950A_0010_0200_0824,
+    0x1202_0004_0801_0200,
+    0x0295_0008_408A_0100,
+    0x40A0_A180_0081_4000,
+    0x0080_8480_2000_4011,
+    0x0800_8280_1000_2000,
+    0x0500_4200_1020_0A00,
+    0x0008_8180_0400_0802,
+    0x0000_8080_0400_0200,
+    0x0000_0400_0102_0810,
+    0x0081_1200_0044_2081,
+    0x0522_4001_8000_2090,
+    0x2800_2000_4040_1000,
+    0x4020_04A1_8010_0481,
+    0x0880_4202_0020_0810,
+    0x000A_0400_8080_0800,
+    0x080A_0004_0400_1020,
+    0x0000_0104_0082_0810,
+    0x4000_D082_0000_4C09,
+    0x0100_8040_0080_0020,
+    0xCC01_0280_2600_4200,
+    0x0081_0020_0100_4010,
+    0x0000_8008_0080_1000,
+    0x0009_8004_0180_2800,
+    0x0002_0009_0200_0410,
+    0x0000_1801_4400_1022,
+    0x0000_8100_C600_2884,
+    0x1200_8000_4000_8024,
+    0x2000_2000_5004_4000,
+    0x0090_8822_0042_0010,
+    0x6A18_1020_0A02_0040,
+    0x0480_0400_0800_8080,
+    0x0206_0011_0816_0014,
+    0xC010_0402_0001_0100,
+    0x0000_0101_8046_000C,
+    0x00B0_4008_8004_2080,
+    0x0040_0020_1008_0220,
+    0x0020_0042_2811_0100,
+    0x0150_0008_0400_4140,
+    0x0028_0080_0400_0980,
+    0x0801_0008_0400_0300,
+    0x2C80_2102_0890_0400,
+    0x0000_0401_3040_8200,
+    0x4001_4091_0025_8001,
+    0x0005_0040_0288_3021,
+    0x0190_41E0_0300_1019,
+    0x8000_2009_0004_1001,
+    0x4021_0010_0204_0801,
+    0x0011_0004_0008_0201,
+    0x1600_0102_1040_8804,
+    0x2010_8100_8400_3042,
+];
+const BISHOP_MAGICS: [u64; 64] = [
+    0x0032_4828_0081_8200,
+    0x0819_0228_2045_0000,
+    0x4242_1082_0488_0008,
+    0x8044_4040_8041_0224,
+    0x1407_1040_0008_0100,
+    0x1B01_1002_1000_4000,
+    0x0B0C_0888_8410_10C1,
+    0x08A0_1082_0110_4020,
+    0x0000_3888_654C_0410,
+    0x0010_2202_0252_0A00,
+    0x4003_0484_0082_0001,
+    0x1007_0220_8200_0002,
+    0x3C22_0404_2004_0A18,
+    0x3440_5088_2008_0030,
+    0x1204_2080_9008_2100,
+    0x0000_9305_0101_2000,
+    0x0010_8420_02D0_0100,
+    0x0202_0004_0448_4208,
+    0x0008_0001_0041_0602,
+    0x0088_0004_0420_0800,
+    0xA004_1002_0202_0232,
+    0x0004_2001_00A0_1002,
+    0x0201_0002_0802_0200,
+    0x2022_0100_2202_0200,
+    0x8010_0410_1004_10B0,
+    0x3010_7048_4801_1100,
+    0x2000_3800_1004_8320,
+    0x8068_0800_0020_2120,
+    0x0001_0100_8010_4000,
+    0x4808_0041_2080_6000,
+    0x5000_8210_0082_3008,
+    0x0004_0100_0050_4224,
+    0x0088_0440_1004_0808,
+    0x0812_0220_0003_2814,
+    0xA000_2808_0011_0202,
+    0x2882_2008_0201_0105,
+    0x0508_0824_0002_4100,
+    0x8001_0102_0003_0800,
+    0x4001_260E_0001_9820,
+    0x0C02_0403_0850_7180,
+    0x0880_8844_4100_9000,
+    0x20A8_4202_A001_1000,
+    0x0001_0410_820C_1000,
+    0x4000_0020_1800_0108,
+    0x0080_0911_2400_4A00,
+    0x0409_0103_0201_0700,
+    0x0088_1288_0204_0040,
+    0x1801_0104_0880_1100,
+    0x0184_0101_9011_0800,
+    0x0000_4042_0821_0000,
+    0x0000_1209_0888_0000,
+    0x9006_1800_2088_4020,
+    0x9000_0040_0488_410A,
+    0x0410_2184_0102_0100,
+    0x0004_D004_2800_8100,
+    0x0002_0214_0400_8400,
+    0x0911_4101_5022_2026,
+    0x0300_2020_8804_1040,
+    0x0100_A061_2412_2800,
+    0x001C_0420_0042_0200,
+    0x0004_0182_0803_0400,
+    0x0044_0404_0508_0200,
+    0x0408_2020_0402_B680,
+    0x0204_2004_1102_0410,
+];
+
+#[derive(Default)]
+struct Magic {
+    /// The squares whose occupancy affects the attacks (the edges of each ray are irrelevant).
+    mask: u64,
+    magic: u64,
+    shift: u32,
+    /// Where this square's attacks start in the shared table.
+    offset: usize,
+}
+
+impl Magic {
+    fn index(&self, occupancy: u64) -> usize {
+        self.offset + ((occupancy & self.mask).wrapping_mul(self.magic) >> self.shift) as usize
+    }
+}
+
+struct Tables {
+    pawn: [[u64; SQUARES]; 2],
+    knight: [u64; SQUARES],
+    king: [u64; SQUARES],
+    rook: [Magic; SQUARES],
+    bishop: [Magic; SQUARES],
+    sliding: Vec<u64>,
+    between: Vec<[u64; SQUARES]>,
+    line: Vec<[u64; SQUARES]>,
+}
+
+static TABLES: OnceLock<Tables> = OnceLock::new();
+
+fn tables() -> &'static Tables {
+    TABLES.get_or_init(Tables::new)
+}
+
+/// Returns the index of the square `dx` files to the right and `dy` ranks up from the given index.
+fn offset(index: usize, dx: i8, dy: i8) -> Option<usize> {
+    let x = (index % BOARD_WIDTH as usize) as i8 + dx;
+    let y = (index / BOARD_WIDTH as usize) as i8 - dy;
+
+    if x < 0 || x >= BOARD_WIDTH as i8 || y < 0 || y >= BOARD_HEIGHT as i8 {
+        return None;
+    }
+
+    Some(y as usize * BOARD_WIDTH as usize + x as usize)
+}
+
+fn leaper(index: usize, offsets: &[(i8, i8)]) -> u64 {
+    offsets
+        .iter()
+        .filter_map(|&(dx, dy)| offset(index, dx, dy))
+        .fold(0, |result, end| result | 1 << end)
+}
+
+/// Walks every direction square by square until the edge of the board or an occupied square.
+fn slide(index: usize, occupancy: u64, directions: &[(i8, i8)]) -> u64 {
+    let mut result = 0;
+
+    for &(dx, dy) in directions {
+        let mut current = index;
+
+        while let Some(end) = offset(current, dx, dy) {
+            result |= 1 << end;
+
+            if occupancy & (1 << end) != 0 {
+                break;
+            }
+
+            current = end;
+        }
+    }
+
+    result
+}
+
+fn relevant_occupancy(index: usize, directions: &[(i8, i8)]) -> u64 {
+    let mut result = 0;
+
+    for &(dx, dy) in directions {
+        let mut current = index;
+
+        while let Some(end) = offset(current, dx, dy) {
+            // Whether or not the last square of a ray is occupied never changes the attacks.
+            if offset(end, dx, dy).is_none() {
+                break;
+            }
+
+            result |= 1 << end;
+            current = end;
+        }
+    }
+
+    result
+}
+
+fn initialize_magics(
+    magics: &mut [Magic; SQUARES],
+    numbers: &[u64; SQUARES],
+    directions: &[(i8, i8)],
+    sliding: &mut Vec<u64>,
+) {
+    for (index, magic) in magics.iter_mut().enumerate() {
+        let mask = relevant_occupancy(index, directions);
+        let bits = mask.count_ones();
+
+        *magic = Magic {
+            mask,
+            magic: numbers[index],
+            shift: 64 - bits,
+            offset: sliding.len(),
+        };
+
+        sliding.resize(sliding.len() + (1 << bits), 0);
+
+        // Enumerate every subset of the mask (the Carry-Rippler trick).
+        let mut occupancy: u64 = 0;
+
+        loop {
+            let entry = magic.index(occupancy);
+
+            sliding[entry] = slide(index, occupancy, directions);
+
+            occupancy = occupancy.wrapping_sub(mask) & mask;
+
+            if occupancy == 0 {
+                break;
+            }
+        }
+    }
+}
+
+impl Tables {
+    fn new() -> Self {
+        let mut pawn = [[0; SQUARES]; 2];
+        let mut knight = [0; SQUARES];
+        let mut king = [0; SQUARES];
+
+        for index in 0..SQUARES {
+            pawn[Color::White as usize][index] = leaper(index, &[(-1, 1), (1, 1)]);
+            pawn[Color::Black as usize][index] = leaper(index, &[(-1, -1), (1, -1)]);
+            knight[index] = leaper(index, &KNIGHT_OFFSETS);
+            king[index] = leaper(index, &KING_OFFSETS);
+        }
+
+        let mut rook = std::array::from_fn(|_| Magic::default());
+        let mut bishop = std::array::from_fn(|_| Magic::default());
+        let mut sliding = Vec::new();
+
+        initialize_magics(&mut rook, &ROOK_MAGICS, &ROOK_DIRECTIONS, &mut sliding);
+        initialize_magics(
+            &mut bishop,
+            &BISHOP_MAGICS,
+            &BISHOP_DIRECTIONS,
+            &mut sliding,
+        );
+
+        let mut between = vec![[0; SQUARES]; SQUARES];
+        let mut line = vec![[0; SQUARES]; SQUARES];
+
+        for a in 0..SQUARES {
+            for directions in [&ROOK_DIRECTIONS, &BISHOP_DIRECTIONS] {
+                let empty = slide(a, 0, directions);
+
+                for b in 0..SQUARES {
+                    if empty & (1 << b) == 0 {
+                        continue;
+                    }
+
+                    between[a][b] = slide(a, 1 << b, directions) & slide(b, 1 << a, directions);
+                    line[a][b] = (empty & slide(b, 0, directions)) | (1 << a) | (1 << b);
+                }
+            }
+        }
+
+        Tables {
+            pawn,
+            knight,
+            king,
+            rook,
+            bishop,
+            sliding,
+            between,
+            line,
+        }
+    }
+}
+
+/// Returns the Coordinates a pawn of the given Color attacks.
+pub fn pawn(color: Color, coordinate: Coordinate) -> Bitboard {
+    Bitboard(tables().pawn[color as usize][coordinate as usize])
+}
+
+pub fn knight(coordinate: Coordinate) -> Bitboard {
+    Bitboard(tables().knight[coordinate as usize])
+}
+
+pub fn king(coordinate: Coordinate) -> Bitboard {
+    Bitboard(tables().king[coordinate as usize])
+}
+
+/// Returns the Coordinates a bishop attacks, including the first occupied square of each diagonal.
+pub fn bishop(coordinate: Coordinate, occupancy: Bitboard) -> Bitboard {
+    let tables = tables();
+
+    Bitboard(tables.sliding[tables.bishop[coordinate as usize].index(occupancy.0)])
+}
+
+/// Returns the Coordinates a rook attacks, including the first occupied square of each line.
+pub fn rook(coordinate: Coordinate, occupancy: Bitboard) -> Bitboard {
+    let tables = tables();
+
+    Bitboard(tables.sliding[tables.rook[coordinate as usize].index(occupancy.0)])
+}
+
+pub fn queen(coordinate: Coordinate, occupancy: Bitboard) -> Bitboard {
+    bishop(coordinate, occupancy) | rook(coordinate, occupancy)
+}
+
+/// Returns the Coordinates strictly between two Coordinates that share a rank, file or diagonal
+/// (or nothing if they do not).
+pub fn between(a: Coordinate, b: Coordinate) -> Bitboard {
+    Bitboard(tables().between[a as usize][b as usize])
+}
+
+/// Returns the entire rank, file or diagonal that two Coordinates share (or nothing if they do
+/// not).
+pub fn line(a: Coordinate, b: Coordinate) -> Bitboard {
+    Bitboard(tables().line[a as usize][b as usize])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sliding_attacks() {
+        // A xorshift generator; any spread of occupancies will do.
+        let mut seed: u64 = 0x2545_F491_4F6C_DD1D;
+        let mut random = || {
+            seed ^= seed << 13;
+            seed ^= seed >> 7;
+            seed ^= seed << 17;
+
+            seed
+        };
+
+        for index in 0..SQUARES {
+            let coordinate = Coordinate::try_from(index as u8).expect("The index should be valid.");
+
+            for _ in 0..64 {
+                let occupancy = random() & random();
+
+                assert_eq!(
+                    rook(coordinate, Bitboard(occupancy)),
+                    Bitboard(slide(index, occupancy, &ROOK_DIRECTIONS))
+                );
+                assert_eq!(
+                    bishop(coordinate, Bitboard(occupancy)),
+                    Bitboard(slide(index, occupancy, &BISHOP_DIRECTIONS))
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_leaper_attacks() {
+        assert_eq!(
+            knight(Coordinate::G1),
+            Bitboard::from(vec![Coordinate::E2, Coordinate::F3, Coordinate::H3])
+        );
+        assert_eq!(
+            king(Coordinate::A8),
+            Bitboard::from(vec![Coordinate::B8, Coordinate::A7, Coordinate::B7])
+        );
+        assert_eq!(
+            pawn(Color::White, Coordinate::E2),
+            Bitboard::from(vec![Coordinate::D3, Coordinate::F3])
+        );
+        assert_eq!(
+            pawn(Color::Black, Coordinate::A7),
+            Bitboard::from(vec![Coordinate::B6])
+        );
+    }
+
+    #[test]
+    fn test_between_and_line() {
+        assert_eq!(
+            between(Coordinate::A1, Coordinate::D4),
+            Bitboard::from(vec![Coordinate::B2, Coordinate::C3])
+        );
+        assert_eq!(
+            between(Coordinate::E1, Coordinate::E8),
+            Bitboard::from(vec![
+                Coordinate::E2,
+                Coordinate::E3,
+                Coordinate::E4,
+                Coordinate::E5,
+                Coordinate::E6,
+                Coordinate::E7,
+            ])
+        );
+        assert_eq!(between(Coordinate::E1, Coordinate::E2), Bitboard::empty());
+        assert_eq!(between(Coordinate::A1, Coordinate::B3), Bitboard::empty());
+
+        assert_eq!(line(Coordinate::C3, Coordinate::B2).population_count(), 8);
+        assert!(line(Coordinate::C3, Coordinate::B2).get(Coordinate::H8));
+        assert_eq!(line(Coordinate::A1, Coordinate::B3), Bitboard::empty());
+    }
+}
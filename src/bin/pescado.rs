@@ -0,0 +1,223 @@
+use std::io::{BufRead, Write};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{mpsc, Arc};
+use std::thread;
+
+const USAGE: &str = "Usage: pescado [tactics <file> [depth <n> | nodes <n> | movetime <ms>]]
+       pescado perft <file> [depth <n>] [hash <mb>]
+       pescado divide <depth> <fen | startpos> [<move>...]";
+
+fn main() {
+    let arguments = std::env::args().skip(1).collect::<Vec<String>>();
+
+    match arguments.first().map(String::as_str) {
+        None => uci(),
+        Some("tactics") => tactics(&arguments[1..]),
+        Some("perft") => perft(&arguments[1..]),
+        Some("divide") => divide(&arguments[1..]),
+        Some(_) => usage(),
+    }
+}
+
+/// Runs a tactical test suite, and reports how many positions were solved.
+fn tactics(arguments: &[String]) {
+    let (path, limit) = match arguments {
+        [path] => (path, chess::suite::SearchLimit::Movetime(1000)),
+        [path, kind, value] => {
+            let limit = match (kind.as_str(), value.parse::<u64>()) {
+                ("depth", Ok(depth)) if depth > 0 && depth <= u8::MAX as u64 => {
+                    chess::suite::SearchLimit::Depth(depth as u8)
+                }
+                ("nodes", Ok(nodes)) => chess::suite::SearchLimit::Nodes(nodes as u128),
+                ("movetime", Ok(movetime)) => chess::suite::SearchLimit::Movetime(movetime),
+                _ => usage(),
+            };
+
+            (path, limit)
+        }
+        _ => usage(),
+    };
+
+    let text = match std::fs::read_to_string(path) {
+        Ok(text) => text,
+        Err(error) => {
+            eprintln!("Error: Could not read {}: {}", path, error);
+            std::process::exit(1);
+        }
+    };
+
+    let report = chess::suite::tactics(&text, limit, |result| println!("{}", result));
+
+    match report {
+        Ok(report) => println!("{}", report),
+        Err(error) => {
+            eprintln!("Error: {}", error);
+            std::process::exit(1);
+        }
+    }
+}
+
+/// Verifies every count of a perft suite, and reports how many of them matched.
+fn perft(arguments: &[String]) {
+    let (path, options) = match arguments.split_first() {
+        Some(split) => split,
+        None => usage(),
+    };
+
+    let mut max_depth = None;
+    let mut hash = None;
+
+    for option in options.chunks(2) {
+        match option {
+            [kind, value] if kind == "depth" => {
+                max_depth = Some(value.parse::<u8>().unwrap_or_else(|_| usage()))
+            }
+            [kind, value] if kind == "hash" => {
+                hash = Some(value.parse::<usize>().unwrap_or_else(|_| usage()))
+            }
+            _ => usage(),
+        }
+    }
+
+    let text = match std::fs::read_to_string(path) {
+        Ok(text) => text,
+        Err(error) => {
+            eprintln!("Error: Could not read {}: {}", path, error);
+            std::process::exit(1);
+        }
+    };
+
+    let report = chess::suite::perft(&text, max_depth, hash, |result| println!("{}", result));
+
+    match report {
+        Ok(report) => {
+            println!("{}", report);
+
+            if report.passed() != report.total() {
+                std::process::exit(1);
+            }
+        }
+        Err(error) => {
+            eprintln!("Error: {}", error);
+            std::process::exit(1);
+        }
+    }
+}
+
+/// Counts the leaves of every move after the given path of moves, in the same format as most
+/// reference engines.
+fn divide(arguments: &[String]) {
+    let (depth, fen, path) = match arguments {
+        [depth, fen, path @ ..] => (depth, fen, path),
+        _ => usage(),
+    };
+
+    let depth = depth.parse::<u8>().unwrap_or_else(|_| usage());
+
+    let state = match fen.as_str() {
+        "startpos" => Ok(chess::State::default()),
+        fen => chess::Fen::try_from(fen).map(chess::State::from),
+    };
+
+    let result = state.and_then(|state| {
+        let path = path
+            .iter()
+            .map(|lan| chess::Lan::try_from(lan.as_str()))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        chess::suite::divide(&state, depth, &path)
+    });
+
+    match result {
+        Ok(moves) => {
+            for (lan, total) in &moves {
+                println!("{}: {}", lan, total);
+            }
+
+            println!();
+            println!(
+                "Nodes searched: {}",
+                moves.iter().map(|(_, total)| total).sum::<u128>()
+            );
+        }
+        Err(error) => {
+            eprintln!("Error: {}", error);
+            std::process::exit(1);
+        }
+    }
+}
+
+fn usage() -> ! {
+    eprintln!("{}", USAGE);
+    std::process::exit(2);
+}
+
+/// Writes a single line to stdout.
+fn respond(message: &str) {
+    let mut stdout = std::io::stdout().lock();
+
+    // There is not much that can be done if the host stopped listening; just ignore it.
+    let _ = writeln!(stdout, "{}", message);
+    let _ = stdout.flush();
+}
+
+/// Speaks UCI over stdin and stdout.
+fn uci() {
+    let mut engine = chess::Pescado::new(|message| respond(&message));
+
+    let stop = engine.stop_handle();
+    let (sender, receiver) = mpsc::channel::<String>();
+
+    // The amount of `go` commands that were sent but have not finished yet.
+    let searching = Arc::new(AtomicUsize::new(0));
+    let reader_searching = Arc::clone(&searching);
+
+    // Commands are read on a separate thread so that `stop` can interrupt a search in progress.
+    thread::spawn(move || {
+        let stdin = std::io::stdin();
+
+        for line in stdin.lock().lines() {
+            let line = match line {
+                Ok(line) => line,
+                Err(_) => break,
+            };
+
+            let command = line.trim();
+
+            if command.is_empty() {
+                continue;
+            }
+
+            if command == "stop" || command == "quit" {
+                stop.stop();
+            }
+
+            // The engine cannot answer while it is searching, but the host still expects to hear
+            // back right away.
+            if command == "isready" && reader_searching.load(Ordering::SeqCst) > 0 {
+                respond("readyok");
+                continue;
+            }
+
+            if command.split_whitespace().next() == Some("go") {
+                reader_searching.fetch_add(1, Ordering::SeqCst);
+            }
+
+            if sender.send(String::from(command)).is_err() {
+                break;
+            }
+        }
+    });
+
+    for command in receiver {
+        engine.send(&command);
+
+        if command.split_whitespace().next() == Some("go") {
+            searching.fetch_sub(1, Ordering::SeqCst);
+        }
+
+        if command == "quit" {
+            break;
+        }
+    }
+}
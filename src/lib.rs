@@ -1,16 +1,51 @@
+mod attacks;
+pub mod pgn;
+pub mod suite;
 mod utils;
 
 use bitflags::bitflags;
 use std::borrow::Borrow;
-use std::cmp::Ordering;
+use std::cell::Cell;
 use std::fmt::Display;
-use std::ops::{BitOr, BitOrAssign, Index, IndexMut};
-use wasm_bindgen::prelude::*;
+use std::ops::{BitAnd, BitAndAssign, BitOr, BitOrAssign, Deref, DerefMut, Index, Not};
+use std::sync::atomic::{self, AtomicBool};
+use std::sync::{Arc, OnceLock};
 
 const BOARD_WIDTH: u8 = 8;
 const BOARD_HEIGHT: u8 = 8;
 const STARTING_PLACEMENT: &str = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR";
 const CHECKMATE_EVALUATION: i16 = i16::MAX - 42;
+const MAX_SEARCH_DEPTH: u8 = 64;
+/// The furthest (in half moves) a search can ever reach from its root, including quiescence.
+const MAX_PLY: usize = 128;
+/// Any score at least this far from zero is a forced mate; the rest of the way to
+/// `CHECKMATE_EVALUATION` is the amount of half moves until checkmate.
+const MATE_BOUND: i16 = CHECKMATE_EVALUATION - MAX_PLY as i16;
+/// The shallowest depth at which passing the turn is worth a search of its own.
+const NULL_MOVE_MIN_DEPTH: u8 = 3;
+/// Late moves are reduced by `LMR_BASE + ln(depth) * ln(index) / LMR_DIVISOR` plies.
+const LMR_BASE: f32 = 0.75;
+const LMR_DIVISOR: f32 = 2.25;
+/// The shallowest depth at which late moves are reduced.
+const LMR_MIN_DEPTH: u8 = 3;
+/// The amount of moves that are always searched at full depth.
+const LMR_MIN_INDEX: usize = 3;
+/// The amount of quiet moves searched at each (shallow) depth before the rest are skipped.
+const LATE_MOVE_PRUNING: [usize; 4] = [0, 6, 10, 16];
+/// Captures that do not lose material are searched before killer moves, which are searched before
+/// countermoves, which are searched before every other quiet move (ordered by their history).
+/// Captures that lose material are searched last.
+const CAPTURE_ORDER: i32 = 1 << 20;
+const KILLER_ORDER: i32 = 1 << 19;
+const COUNTERMOVE_ORDER: i32 = 1 << 18;
+/// The most a quiet move's history can ever grow (or shrink) to.
+const MAX_HISTORY: i32 = 1 << 14;
+/// The amount of half moves without a capture or pawn move after which a draw can be claimed.
+const FIFTY_MOVE_RULE: usize = 100;
+/// The default size (in megabytes) of the transposition table.
+const DEFAULT_HASH_SIZE: usize = 16;
+const MIN_HASH_SIZE: usize = 1;
+const MAX_HASH_SIZE: usize = 1024;
 
 #[derive(Debug, PartialEq, Eq)]
 enum ChessErrorKind {
@@ -34,7 +69,7 @@ impl Display for ChessError {
 }
 
 #[derive(Debug, PartialEq, Eq, Copy, Clone)]
-enum Color {
+pub enum Color {
     White,
     Black,
 }
@@ -98,7 +133,7 @@ impl Display for Color {
 }
 
 #[derive(Debug, PartialEq, Eq, Copy, Clone)]
-enum PieceKind {
+pub enum PieceKind {
     Pawn,
     Knight,
     Bishop,
@@ -337,8 +372,9 @@ impl Display for CastlingAbility {
     }
 }
 
+/// A square on the board.
 #[derive(Debug, PartialEq, Eq, Copy, Clone)]
-enum Coordinate {
+pub enum Coordinate {
     A8 = 0,
     B8 = 1,
     C8 = 2,
@@ -409,85 +445,93 @@ impl TryFrom<u8> for Coordinate {
     type Error = ChessError;
 
     fn try_from(value: u8) -> Result<Self, Self::Error> {
-        match value {
-            0 => Ok(Coordinate::A8),
-            1 => Ok(Coordinate::B8),
-            2 => Ok(Coordinate::C8),
-            3 => Ok(Coordinate::D8),
-            4 => Ok(Coordinate::E8),
-            5 => Ok(Coordinate::F8),
-            6 => Ok(Coordinate::G8),
-            7 => Ok(Coordinate::H8),
-            8 => Ok(Coordinate::A7),
-            9 => Ok(Coordinate::B7),
-            10 => Ok(Coordinate::C7),
-            11 => Ok(Coordinate::D7),
-            12 => Ok(Coordinate::E7),
-            13 => Ok(Coordinate::F7),
-            14 => Ok(Coordinate::G7),
-            15 => Ok(Coordinate::H7),
-            16 => Ok(Coordinate::A6),
-            17 => Ok(Coordinate::B6),
-            18 => Ok(Coordinate::C6),
-            19 => Ok(Coordinate::D6),
-            20 => Ok(Coordinate::E6),
-            21 => Ok(Coordinate::F6),
-            22 => Ok(Coordinate::G6),
-            23 => Ok(Coordinate::H6),
-            24 => Ok(Coordinate::A5),
-            25 => Ok(Coordinate::B5),
-            26 => Ok(Coordinate::C5),
-            27 => Ok(Coordinate::D5),
-            28 => Ok(Coordinate::E5),
-            29 => Ok(Coordinate::F5),
-            30 => Ok(Coordinate::G5),
-            31 => Ok(Coordinate::H5),
-            32 => Ok(Coordinate::A4),
-            33 => Ok(Coordinate::B4),
-            34 => Ok(Coordinate::C4),
-            35 => Ok(Coordinate::D4),
-            36 => Ok(Coordinate::E4),
-            37 => Ok(Coordinate::F4),
-            38 => Ok(Coordinate::G4),
-            39 => Ok(Coordinate::H4),
-            40 => Ok(Coordinate::A3),
-            41 => Ok(Coordinate::B3),
-            42 => Ok(Coordinate::C3),
-            43 => Ok(Coordinate::D3),
-            44 => Ok(Coordinate::E3),
-            45 => Ok(Coordinate::F3),
-            46 => Ok(Coordinate::G3),
-            47 => Ok(Coordinate::H3),
-            48 => Ok(Coordinate::A2),
-            49 => Ok(Coordinate::B2),
-            50 => Ok(Coordinate::C2),
-            51 => Ok(Coordinate::D2),
-            52 => Ok(Coordinate::E2),
-            53 => Ok(Coordinate::F2),
-            54 => Ok(Coordinate::G2),
-            55 => Ok(Coordinate::H2),
-            56 => Ok(Coordinate::A1),
-            57 => Ok(Coordinate::B1),
-            58 => Ok(Coordinate::C1),
-            59 => Ok(Coordinate::D1),
-            60 => Ok(Coordinate::E1),
-            61 => Ok(Coordinate::F1),
-            62 => Ok(Coordinate::G1),
-            63 => Ok(Coordinate::H1),
-            _ => Err(ChessError(
+        Coordinate::ALL
+            .get(value as usize)
+            .copied()
+            .ok_or(ChessError(
                 ChessErrorKind::IndexOutOfRange,
                 "The given index is too big to be a Coordinate.",
-            )),
-        }
+            ))
     }
 }
 
 impl Coordinate {
-    fn x(&self) -> u8 {
+    /// Every Coordinate, ordered by index.
+    const ALL: [Coordinate; (BOARD_WIDTH * BOARD_HEIGHT) as usize] = [
+        Coordinate::A8,
+        Coordinate::B8,
+        Coordinate::C8,
+        Coordinate::D8,
+        Coordinate::E8,
+        Coordinate::F8,
+        Coordinate::G8,
+        Coordinate::H8,
+        Coordinate::A7,
+        Coordinate::B7,
+        Coordinate::C7,
+        Coordinate::D7,
+        Coordinate::E7,
+        Coordinate::F7,
+        Coordinate::G7,
+        Coordinate::H7,
+        Coordinate::A6,
+        Coordinate::B6,
+        Coordinate::C6,
+        Coordinate::D6,
+        Coordinate::E6,
+        Coordinate::F6,
+        Coordinate::G6,
+        Coordinate::H6,
+        Coordinate::A5,
+        Coordinate::B5,
+        Coordinate::C5,
+        Coordinate::D5,
+        Coordinate::E5,
+        Coordinate::F5,
+        Coordinate::G5,
+        Coordinate::H5,
+        Coordinate::A4,
+        Coordinate::B4,
+        Coordinate::C4,
+        Coordinate::D4,
+        Coordinate::E4,
+        Coordinate::F4,
+        Coordinate::G4,
+        Coordinate::H4,
+        Coordinate::A3,
+        Coordinate::B3,
+        Coordinate::C3,
+        Coordinate::D3,
+        Coordinate::E3,
+        Coordinate::F3,
+        Coordinate::G3,
+        Coordinate::H3,
+        Coordinate::A2,
+        Coordinate::B2,
+        Coordinate::C2,
+        Coordinate::D2,
+        Coordinate::E2,
+        Coordinate::F2,
+        Coordinate::G2,
+        Coordinate::H2,
+        Coordinate::A1,
+        Coordinate::B1,
+        Coordinate::C1,
+        Coordinate::D1,
+        Coordinate::E1,
+        Coordinate::F1,
+        Coordinate::G1,
+        Coordinate::H1,
+    ];
+
+    /// Returns the file of the Coordinate, where zero is the a-file.
+    pub fn x(&self) -> u8 {
         (*self) as u8 % BOARD_WIDTH
     }
 
-    fn y(&self) -> u8 {
+    /// Returns the rank of the Coordinate counting from the top of the board, where zero is the
+    /// eighth rank.
+    pub fn y(&self) -> u8 {
         (*self) as u8 / BOARD_WIDTH
     }
 
@@ -569,7 +613,7 @@ impl TryFrom<&str> for Coordinate {
     }
 }
 
-impl<'a> From<Coordinate> for &'a str {
+impl From<Coordinate> for &str {
     fn from(value: Coordinate) -> Self {
         match value {
             Coordinate::A8 => "a8",
@@ -648,11 +692,12 @@ impl Display for Coordinate {
     }
 }
 
+/// A move in long algebraic notation (e.g. e2e4, e7e8q).
 #[derive(Debug, PartialEq, Eq, Copy, Clone)]
-struct Lan {
-    start: Coordinate,
-    end: Coordinate,
-    promotion: Option<PieceKind>,
+pub struct Lan {
+    pub start: Coordinate,
+    pub end: Coordinate,
+    pub promotion: Option<PieceKind>,
 }
 
 impl TryFrom<&str> for Lan {
@@ -724,6 +769,111 @@ impl Display for Lan {
     }
 }
 
+/// Describes what a Move does besides moving a piece from one Coordinate to another.
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+enum MoveFlag {
+    Quiet,
+    /// A pawn advancing two squares.
+    DoublePush,
+    KingCastle,
+    QueenCastle,
+    Capture,
+    EnPassant,
+    Promotion(PieceKind),
+    PromotionCapture(PieceKind),
+}
+
+/// A move packed into 16 bits: six bits for the start Coordinate, six bits for the end
+/// Coordinate, and four bits for its MoveFlag.
+///
+/// Unlike a Lan, a Move knows what kind of move it is, so it has to be created from the position
+/// it is played in (see `Board::encode`).
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+struct Move(u16);
+
+impl Move {
+    const PROMOTION: u16 = 0b1000;
+    const PROMOTION_CAPTURE: u16 = 0b1100;
+
+    fn new(start: Coordinate, end: Coordinate, flag: MoveFlag) -> Self {
+        // Promotions only ever need to tell the four kinds between a knight and a queen apart.
+        let promotion = |kind: PieceKind| kind as u16 - PieceKind::Knight as u16;
+
+        let flag = match flag {
+            MoveFlag::Quiet => 0,
+            MoveFlag::DoublePush => 1,
+            MoveFlag::KingCastle => 2,
+            MoveFlag::QueenCastle => 3,
+            MoveFlag::Capture => 4,
+            MoveFlag::EnPassant => 5,
+            MoveFlag::Promotion(kind) => Move::PROMOTION | promotion(kind),
+            MoveFlag::PromotionCapture(kind) => Move::PROMOTION_CAPTURE | promotion(kind),
+        };
+
+        Move(start as u16 | (end as u16) << 6 | flag << 12)
+    }
+
+    fn start(&self) -> Coordinate {
+        Coordinate::try_from((self.0 & 0x3f) as u8)
+            .expect("Six bits should always index a valid Coordinate.")
+    }
+
+    fn end(&self) -> Coordinate {
+        Coordinate::try_from((self.0 >> 6 & 0x3f) as u8)
+            .expect("Six bits should always index a valid Coordinate.")
+    }
+
+    fn flag(&self) -> MoveFlag {
+        let flag = self.0 >> 12;
+
+        match flag {
+            0 => MoveFlag::Quiet,
+            1 => MoveFlag::DoublePush,
+            2 => MoveFlag::KingCastle,
+            3 => MoveFlag::QueenCastle,
+            4 => MoveFlag::Capture,
+            5 => MoveFlag::EnPassant,
+            _ => {
+                let kind = match flag & 0b11 {
+                    0 => PieceKind::Knight,
+                    1 => PieceKind::Bishop,
+                    2 => PieceKind::Rook,
+                    _ => PieceKind::Queen,
+                };
+
+                match flag & Move::PROMOTION_CAPTURE {
+                    Move::PROMOTION_CAPTURE => MoveFlag::PromotionCapture(kind),
+                    _ => MoveFlag::Promotion(kind),
+                }
+            }
+        }
+    }
+
+    fn is_capture(&self) -> bool {
+        matches!(
+            self.flag(),
+            MoveFlag::Capture | MoveFlag::EnPassant | MoveFlag::PromotionCapture(_)
+        )
+    }
+
+    fn promotion(&self) -> Option<PieceKind> {
+        match self.flag() {
+            MoveFlag::Promotion(kind) | MoveFlag::PromotionCapture(kind) => Some(kind),
+            _ => None,
+        }
+    }
+}
+
+impl From<Move> for Lan {
+    fn from(value: Move) -> Self {
+        Lan {
+            start: value.start(),
+            end: value.end(),
+            promotion: value.promotion(),
+        }
+    }
+}
+
 #[derive(Debug, PartialEq, Eq, Clone)]
 struct Placement(String);
 
@@ -1113,296 +1263,495 @@ impl Display for Fen {
     }
 }
 
-#[derive(Debug, PartialEq, Eq)]
-enum MoveModifier {
-    Castle,
-    EnPassant,
-    Promotion,
+/// A position in Extended Position Description, which is how most test suites are distributed.
+///
+/// Learn more about EPD here: https://www.chessprogramming.org/Extended_Position_Description
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Epd {
+    fen: Fen,
+    /// Operations in the order they appear (e.g. `("bm", vec!["Qxf7+"])`).
+    pub operations: Vec<(String, Vec<String>)>,
+}
+
+impl Epd {
+    /// Returns the operands of the first operation with the given opcode.
+    pub fn operation(&self, opcode: &str) -> Option<&[String]> {
+        self.operations
+            .iter()
+            .find(|(key, _)| key == opcode)
+            .map(|(_, operands)| operands.as_slice())
+    }
+
+    /// Returns the id of the position (e.g. `WAC.001`).
+    pub fn id(&self) -> Option<&str> {
+        self.operation("id")?.first().map(|id| id.as_str())
+    }
+
+    /// Returns the primary comment of the position.
+    pub fn comment(&self) -> Option<&str> {
+        self.operation("c0")?
+            .first()
+            .map(|comment| comment.as_str())
+    }
+
+    /// Returns the best moves (i.e. the `bm` opcode), resolved against the position.
+    pub fn best_moves(&self) -> Result<Vec<Lan>, ChessError> {
+        self.resolve_moves("bm")
+    }
+
+    /// Returns the moves that should be avoided (i.e. the `am` opcode), resolved against the
+    /// position.
+    pub fn avoid_moves(&self) -> Result<Vec<Lan>, ChessError> {
+        self.resolve_moves("am")
+    }
+
+    /// Returns the depth the position was analyzed to (i.e. the `acd` opcode).
+    pub fn analysis_depth(&self) -> Option<u8> {
+        self.operation("acd")?.first()?.parse().ok()
+    }
+
+    /// Returns the centipawn evaluation of the position (i.e. the `ce` opcode).
+    pub fn evaluation(&self) -> Option<i16> {
+        self.operation("ce")?.first()?.parse().ok()
+    }
+
+    fn resolve_moves(&self, opcode: &str) -> Result<Vec<Lan>, ChessError> {
+        let state = State::from(self.fen.clone());
+
+        self.operation(opcode)
+            .unwrap_or_default()
+            .iter()
+            .map(|san| state.parse_san(san))
+            .collect()
+    }
+}
+
+impl TryFrom<&str> for Epd {
+    type Error = ChessError;
+
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        let mut sections = value.trim_start().splitn(5, char::is_whitespace);
+        let mut fields = Vec::with_capacity(4);
+
+        for _ in 0..4 {
+            match sections.next() {
+                Some(field) if !field.is_empty() => fields.push(field),
+                _ => {
+                    return Err(ChessError(
+                        ChessErrorKind::InvalidString,
+                        "A valid EPD must start with four sections separated by whitespace.",
+                    ))
+                }
+            }
+        }
+
+        let mut operations: Vec<(String, Vec<String>)> = Vec::new();
+        let mut characters = sections.next().unwrap_or("").chars().peekable();
+        let mut operands: Option<(String, Vec<String>)> = None;
+
+        while let Some(character) = characters.next() {
+            match character {
+                _ if character.is_whitespace() => (),
+                ';' => match operands.take() {
+                    Some(operation) => operations.push(operation),
+                    None => {
+                        return Err(ChessError(
+                            ChessErrorKind::InvalidString,
+                            "Expected an opcode before the semicolon.",
+                        ))
+                    }
+                },
+                '"' => {
+                    let mut string = String::new();
+
+                    loop {
+                        match characters.next() {
+                            Some('"') => break,
+                            Some(character) => string.push(character),
+                            None => {
+                                return Err(ChessError(
+                                    ChessErrorKind::InvalidString,
+                                    "A string operand was never closed.",
+                                ))
+                            }
+                        }
+                    }
+
+                    match &mut operands {
+                        Some((_, list)) => list.push(string),
+                        None => {
+                            return Err(ChessError(
+                                ChessErrorKind::InvalidString,
+                                "Expected an opcode before the string operand.",
+                            ))
+                        }
+                    }
+                }
+                _ => {
+                    let mut token = String::from(character);
+
+                    while let Some(character) = characters
+                        .next_if(|character| !character.is_whitespace() && *character != ';')
+                    {
+                        token.push(character);
+                    }
+
+                    match &mut operands {
+                        Some((_, list)) => list.push(token),
+                        None => operands = Some((token, Vec::new())),
+                    }
+                }
+            }
+        }
+
+        if operands.is_some() {
+            return Err(ChessError(
+                ChessErrorKind::InvalidString,
+                "Every operation must end with a semicolon.",
+            ));
+        }
+
+        // The half move clock and full move number can be provided as operations.
+        let counter = |opcode: &str, default: &str| {
+            operations
+                .iter()
+                .find(|(key, _)| key == opcode)
+                .and_then(|(_, operands)| operands.first().cloned())
+                .unwrap_or_else(|| String::from(default))
+        };
+
+        let fen = format!(
+            "{} {} {} {} {} {}",
+            fields[0],
+            fields[1],
+            fields[2],
+            fields[3],
+            counter("hmvc", "0"),
+            counter("fmvn", "1")
+        );
+
+        let fen = Fen::try_from(fen.as_str())?;
+
+        Ok(Epd { fen, operations })
+    }
+}
+
+impl From<&Epd> for String {
+    fn from(value: &Epd) -> Self {
+        let fen = value.fen.to_string();
+        let mut result = fen
+            .split_whitespace()
+            .take(4)
+            .collect::<Vec<&str>>()
+            .join(" ");
+
+        for (opcode, operands) in &value.operations {
+            // Comments and ids are always strings; anything else only needs quotes if it would
+            // not survive being parsed again.
+            let string_opcode = opcode == "id"
+                || (opcode.len() == 2
+                    && opcode.starts_with('c')
+                    && opcode.ends_with(|c: char| c.is_ascii_digit()));
+
+            result.push(' ');
+            result.push_str(opcode);
+
+            for operand in operands {
+                let needs_quotes = string_opcode
+                    || operand.is_empty()
+                    || operand.contains(|c: char| c.is_whitespace() || c == ';' || c == '"');
+
+                result.push(' ');
+
+                if needs_quotes {
+                    result.push_str(&format!("\"{}\"", operand));
+                } else {
+                    result.push_str(operand);
+                }
+            }
+
+            result.push(';');
+        }
+
+        result
+    }
+}
+
+impl Display for Epd {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", String::from(self))
+    }
+}
+
+impl From<Epd> for State {
+    fn from(value: Epd) -> Self {
+        State::from(value.fen)
+    }
 }
 
 #[derive(Debug, PartialEq, Eq)]
 struct MoveUndoer {
-    lan: Lan,
+    mv: Move,
     /// The Piece that previously occupied the square.
     previous: Option<Piece>,
-    modifer: Option<MoveModifier>,
 }
 
 #[derive(Debug, PartialEq, Eq, Clone, Copy)]
 struct Board {
     pieces: [Option<Piece>; (BOARD_WIDTH * BOARD_HEIGHT) as usize],
+    /// Every Coordinate occupied by each Color.
+    colors: [Bitboard; 2],
+    /// Every Coordinate occupied by each PieceKind (regardless of its Color).
+    kinds: [Bitboard; 6],
 }
 
 impl Board {
-    fn make_move(&mut self, lan: Lan) -> Result<MoveUndoer, ChessError> {
-        let start = self.pieces[lan.start as usize];
-        let previous = self.pieces[lan.end as usize];
+    /// Places the given Piece (or nothing) on the given Coordinate while keeping the Bitboards in
+    /// sync.
+    fn set(&mut self, coordinate: Coordinate, piece: Option<Piece>) {
+        if let Some(Piece(color, kind)) = self.pieces[coordinate as usize] {
+            self.colors[color as usize].set(coordinate, false);
+            self.kinds[kind as usize].set(coordinate, false);
+        }
 
-        let dx = lan.end.x() as i8 - lan.start.x() as i8;
-        let dy = lan.end.y() as i8 - lan.start.y() as i8;
+        if let Some(Piece(color, kind)) = piece {
+            self.colors[color as usize].set(coordinate, true);
+            self.kinds[kind as usize].set(coordinate, true);
+        }
 
-        match start {
-            Some(piece) => {
-                match piece {
-                    Piece(color, PieceKind::Pawn) => {
-                        if let Some(promotion) = lan.promotion {
-                            self.pieces[lan.start as usize] = None;
-                            self.pieces[lan.end as usize] = Some(Piece(color, promotion));
-
-                            return Ok(MoveUndoer {
-                                lan,
-                                previous,
-                                modifer: Some(MoveModifier::Promotion),
-                            });
-                        }
+        self.pieces[coordinate as usize] = piece;
+    }
 
-                        // Deal with an en passant (Holy hell).
-                        if dx != 0 && previous.is_none() {
-                            let direction: i8 = dy.signum();
-                            let coordinate = lan.end.try_move(0, direction).expect(
-                                    "If a pawn captured en passant then the coordinate above and below the target should always be valid.",
-                                );
+    fn occupancy(&self) -> Bitboard {
+        self.colors[Color::White as usize] | self.colors[Color::Black as usize]
+    }
 
-                            self.pieces[coordinate as usize] = None;
+    fn occupancy_of(&self, color: Color) -> Bitboard {
+        self.colors[color as usize]
+    }
 
-                            self.pieces[lan.start as usize] = None;
-                            self.pieces[lan.end as usize] = start;
+    /// Returns every Coordinate occupied by the given Color's pieces of the given kind.
+    fn find(&self, color: Color, kind: PieceKind) -> Bitboard {
+        self.colors[color as usize] & self.kinds[kind as usize]
+    }
 
-                            return Ok(MoveUndoer {
-                                lan,
-                                previous,
-                                modifer: Some(MoveModifier::EnPassant),
-                            });
-                        }
+    /// Returns every piece of either Color that attacks the given Coordinate, as if only the pieces
+    /// of the given occupancy were on the board.
+    fn attackers_to(&self, target: Coordinate, occupancy: Bitboard) -> Bitboard {
+        let diagonals =
+            self.kinds[PieceKind::Bishop as usize] | self.kinds[PieceKind::Queen as usize];
+        let orthogonals =
+            self.kinds[PieceKind::Rook as usize] | self.kinds[PieceKind::Queen as usize];
 
-                        self.pieces[lan.start as usize] = None;
-                        self.pieces[lan.end as usize] = start;
+        // Every piece attacks the target from the same squares the target would attack it from.
+        let attackers = (attacks::pawn(Color::White, target)
+            & self.find(Color::Black, PieceKind::Pawn))
+            | (attacks::pawn(Color::Black, target) & self.find(Color::White, PieceKind::Pawn))
+            | (attacks::knight(target) & self.kinds[PieceKind::Knight as usize])
+            | (attacks::bishop(target, occupancy) & diagonals)
+            | (attacks::rook(target, occupancy) & orthogonals)
+            | (attacks::king(target) & self.kinds[PieceKind::King as usize]);
 
-                        Ok(MoveUndoer {
-                            lan,
-                            previous,
-                            modifer: None,
-                        })
-                    }
-                    Piece(color, PieceKind::King) => {
-                        // If the king castled then make sure to also move the rook.
-                        if dx.abs() == 2 {
-                            let y = match color {
-                                Color::White => BOARD_HEIGHT - 1,
-                                Color::Black => 0,
-                            };
-
-                            let (rook_start, rook_end) = match dx.cmp(&0) {
-                                // Castling king side.
-                                Ordering::Greater => {
-                                    let x = BOARD_WIDTH - 1;
-                                    let index = y * BOARD_WIDTH + x;
-
-                                    (index, index - 2)
-                                }
-                                // Castling queen side.
-                                Ordering::Less => {
-                                    let x = 0;
-                                    let index = y * BOARD_WIDTH + x;
+        attackers & occupancy
+    }
 
-                                    (index, index + 3)
-                                }
-                                _ => unreachable!(),
-                            };
+    /// Whether or not the given Color has any pieces besides pawns and their king.
+    fn has_non_pawn_material(&self, color: Color) -> bool {
+        let pawns_and_king = self.find(color, PieceKind::Pawn) | self.find(color, PieceKind::King);
 
-                            self.pieces[rook_start as usize] = None;
-                            self.pieces[rook_end as usize] = Some(Piece(color, PieceKind::Rook));
+        !(self.occupancy_of(color) & !pawns_and_king).is_empty()
+    }
 
-                            self.pieces[lan.start as usize] = None;
-                            self.pieces[lan.end as usize] = start;
+    /// Returns every occupied Coordinate except for the opponent's king, which should not be able
+    /// to block the given Color's attackers (otherwise the king could step back along a line of
+    /// sight).
+    fn danger_occupancy(&self, color: Color) -> Bitboard {
+        self.occupancy() & !self.find(color.opponent(), PieceKind::King)
+    }
 
-                            return Ok(MoveUndoer {
-                                lan,
-                                previous,
-                                modifer: Some(MoveModifier::Castle),
-                            });
-                        }
+    /// Works out which kind of Move the given Lan describes on the current Board.
+    fn encode(&self, lan: Lan) -> Result<Move, ChessError> {
+        let Piece(color, kind) = self[lan.start].ok_or(ChessError(
+            ChessErrorKind::TargetIsNone,
+            "Cannot move a piece that does not exist.",
+        ))?;
 
-                        self.pieces[lan.start as usize] = None;
-                        self.pieces[lan.end as usize] = start;
+        let capture = self[lan.end].is_some();
+        let dx = lan.end.x() as i8 - lan.start.x() as i8;
+        let dy = lan.end.y() as i8 - lan.start.y() as i8;
 
-                        Ok(MoveUndoer {
-                            lan,
-                            previous,
-                            modifer: None,
-                        })
-                    }
-                    _ => {
-                        if lan.promotion.is_some() {
-                            return Err(ChessError(
-                                ChessErrorKind::InvalidPromotion,
-                                "Only pawns can be promoted.",
-                            ));
-                        }
+        if let Some(promotion) = lan.promotion {
+            if kind != PieceKind::Pawn {
+                return Err(ChessError(
+                    ChessErrorKind::InvalidPromotion,
+                    "Only pawns can be promoted.",
+                ));
+            }
 
-                        self.pieces[lan.start as usize] = None;
-                        self.pieces[lan.end as usize] = start;
+            if let PieceKind::Pawn | PieceKind::King = promotion {
+                return Err(ChessError(
+                    ChessErrorKind::InvalidPromotion,
+                    "A pawn can only be promoted to a knight, bishop, rook, or queen.",
+                ));
+            }
+        }
 
-                        Ok(MoveUndoer {
-                            lan,
-                            previous,
-                            modifer: None,
-                        })
-                    }
-                }
+        // Castling is the only way a king moves two files, and capturing en passant is the only way
+        // a pawn changes files without capturing; anything else shaped like them is not a move.
+        if kind == PieceKind::King && dx.abs() == 2 {
+            let castle = matches!(
+                (color, lan.start, lan.end),
+                (
+                    Color::White,
+                    Coordinate::E1,
+                    Coordinate::G1 | Coordinate::C1
+                ) | (
+                    Color::Black,
+                    Coordinate::E8,
+                    Coordinate::G8 | Coordinate::C8
+                )
+            );
+
+            if !castle {
+                return Err(ChessError(
+                    ChessErrorKind::Other,
+                    "A king can only move two files by castling from its home square.",
+                ));
             }
-            _ => Err(ChessError(
-                ChessErrorKind::TargetIsNone,
-                "Cannot move a piece that does not exist.",
-            )),
         }
-    }
 
-    fn unmake_move(&mut self, undoer: MoveUndoer) {
-        let piece = self.pieces[undoer.lan.end as usize];
+        if kind == PieceKind::Pawn && dx != 0 && !capture && lan.promotion.is_none() {
+            // En passant targets are always on the third or sixth rank.
+            if lan.end.y() != 2 && lan.end.y() != 5 {
+                return Err(ChessError(
+                    ChessErrorKind::Other,
+                    "A pawn can only change files without capturing by capturing en passant.",
+                ));
+            }
+        }
 
-        self.pieces[undoer.lan.start as usize] = piece;
-        self.pieces[undoer.lan.end as usize] = undoer.previous;
+        let flag = match (kind, lan.promotion) {
+            (_, Some(promotion)) if capture => MoveFlag::PromotionCapture(promotion),
+            (_, Some(promotion)) => MoveFlag::Promotion(promotion),
+            (PieceKind::Pawn, _) if dy.abs() == 2 => MoveFlag::DoublePush,
+            // Pawns can only change files without capturing by capturing en passant.
+            (PieceKind::Pawn, _) if dx != 0 && !capture => MoveFlag::EnPassant,
+            (PieceKind::King, _) if dx == 2 => MoveFlag::KingCastle,
+            (PieceKind::King, _) if dx == -2 => MoveFlag::QueenCastle,
+            _ if capture => MoveFlag::Capture,
+            _ => MoveFlag::Quiet,
+        };
 
-        if let Some(modifier) = undoer.modifer {
-            let piece =
-                piece.expect("When unmaking a move a Lan's end should always index a Some Piece.");
+        Ok(Move::new(lan.start, lan.end, flag))
+    }
 
-            match modifier {
-                MoveModifier::Castle => {
-                    let dx = undoer.lan.end.x() as i8 - undoer.lan.start.x() as i8;
+    /// Returns where the rook starts and ends when the king castles to the given Coordinate.
+    fn castling_rook(end: Coordinate) -> (Coordinate, Coordinate) {
+        match end {
+            Coordinate::G1 => (Coordinate::H1, Coordinate::F1),
+            Coordinate::C1 => (Coordinate::A1, Coordinate::D1),
+            Coordinate::G8 => (Coordinate::H8, Coordinate::F8),
+            Coordinate::C8 => (Coordinate::A8, Coordinate::D8),
+            _ => unreachable!("A king can only ever castle to the c or g file of its home rank."),
+        }
+    }
 
-                    let y = match piece.0 {
-                        Color::White => BOARD_HEIGHT - 1,
-                        Color::Black => 0,
-                    };
+    /// Returns the Coordinate of the pawn that the given en passant capture removes.
+    fn en_passant_capture(mv: Move) -> Coordinate {
+        let dy = mv.end().y() as i8 - mv.start().y() as i8;
 
-                    let (rook_start, rook_end) = match dx.cmp(&0) {
-                        // Castling king side.
-                        Ordering::Greater => {
-                            let x = BOARD_WIDTH - 1;
-                            let index = y * BOARD_WIDTH + x;
+        mv.end().try_move(0, dy.signum()).expect(
+            "If a pawn captured en passant then the coordinate above and below the target should always be valid.",
+        )
+    }
 
-                            (index, index - 2)
-                        }
-                        // Castling queen side.
-                        Ordering::Less => {
-                            let x = 0;
-                            let index = y * BOARD_WIDTH + x;
+    fn make_move(&mut self, mv: Move) -> Result<MoveUndoer, ChessError> {
+        let start = self.pieces[mv.start() as usize];
+        let previous = self.pieces[mv.end() as usize];
 
-                            (index, index + 3)
-                        }
-                        _ => unreachable!(),
-                    };
+        let color = match start {
+            Some(Piece(color, _)) => color,
+            None => {
+                return Err(ChessError(
+                    ChessErrorKind::TargetIsNone,
+                    "Cannot move a piece that does not exist.",
+                ))
+            }
+        };
 
-                    self.pieces[rook_start as usize] = Some(Piece(piece.0, PieceKind::Rook));
-                    self.pieces[rook_end as usize] = None;
-                }
-                MoveModifier::EnPassant => {
-                    let dy = undoer.lan.end.y() as i8 - undoer.lan.start.y() as i8;
-                    let direction = dy.signum();
+        self.set(mv.start(), None);
 
-                    let coordinate = undoer.lan.end.try_move(0, direction).expect("The coordinates above and below an en passant target should always be valid.");
+        match mv.flag() {
+            MoveFlag::Promotion(kind) | MoveFlag::PromotionCapture(kind) => {
+                self.set(mv.end(), Some(Piece(color, kind)));
+            }
+            // Deal with an en passant (Holy hell).
+            MoveFlag::EnPassant => {
+                self.set(Board::en_passant_capture(mv), None);
+                self.set(mv.end(), start);
+            }
+            // If the king castled then make sure to also move the rook.
+            MoveFlag::KingCastle | MoveFlag::QueenCastle => {
+                let (rook_start, rook_end) = Board::castling_rook(mv.end());
 
-                    self.pieces[coordinate as usize] =
-                        Some(Piece(piece.0.opponent(), PieceKind::Pawn));
-                }
-                MoveModifier::Promotion => {
-                    self.pieces[undoer.lan.start as usize] = Some(Piece(piece.0, PieceKind::Pawn));
-                }
+                self.set(rook_start, None);
+                self.set(rook_end, Some(Piece(color, PieceKind::Rook)));
+                self.set(mv.end(), start);
             }
+            _ => self.set(mv.end(), start),
         }
+
+        Ok(MoveUndoer { mv, previous })
     }
 
-    fn walk_dangerously(&self, danger_zone: &mut Bitboard, start: Coordinate, dx: i8, dy: i8) {
-        let size = BOARD_WIDTH.max(BOARD_HEIGHT) as i8;
-        let opponent = self.pieces[start as usize]
-            .expect("The starting Coordinate should always index a Some piece")
-            .0
-            .opponent();
+    fn unmake_move(&mut self, undoer: MoveUndoer) {
+        let MoveUndoer { mv, previous } = undoer;
 
-        for i in 1..size {
-            if let Ok(end) = start.try_move(i * dx, i * dy) {
-                match self.pieces[end as usize] {
-                    Some(piece) => {
-                        danger_zone.set(end, true);
+        let piece = self.pieces[mv.end() as usize]
+            .expect("When unmaking a move a Move's end should always index a Some Piece.");
 
-                        match piece {
-                            // The king should not be able to block attackers.
-                            Piece(color, PieceKind::King) if color == opponent => continue,
-                            _ => (),
-                        }
+        self.set(mv.end(), previous);
 
-                        break;
-                    }
-                    None => danger_zone.set(end, true),
-                }
-            } else {
-                break;
+        match mv.flag() {
+            MoveFlag::Promotion(_) | MoveFlag::PromotionCapture(_) => {
+                self.set(mv.start(), Some(Piece(piece.0, PieceKind::Pawn)));
+            }
+            MoveFlag::EnPassant => {
+                self.set(mv.start(), Some(piece));
+                self.set(
+                    Board::en_passant_capture(mv),
+                    Some(Piece(piece.0.opponent(), PieceKind::Pawn)),
+                );
+            }
+            MoveFlag::KingCastle | MoveFlag::QueenCastle => {
+                let (rook_start, rook_end) = Board::castling_rook(mv.end());
+
+                self.set(mv.start(), Some(piece));
+                self.set(rook_end, None);
+                self.set(rook_start, Some(Piece(piece.0, PieceKind::Rook)));
             }
+            _ => self.set(mv.start(), Some(piece)),
         }
     }
 
     fn generate_pawn_danger_zone(&self, coordinate: Coordinate) -> Option<Bitboard> {
         match self.pieces[coordinate as usize] {
-            Some(Piece(color, PieceKind::Pawn)) => {
-                let mut result = Bitboard::empty();
-
-                let dy = match color {
-                    Color::White => 1,
-                    Color::Black => -1,
-                };
-
-                if let Ok(end) = coordinate.try_move(-1, dy) {
-                    result.set(end, true);
-                }
-                if let Ok(end) = coordinate.try_move(1, dy) {
-                    result.set(end, true);
-                }
-
-                Some(result)
-            }
+            Some(Piece(color, PieceKind::Pawn)) => Some(attacks::pawn(color, coordinate)),
             _ => None,
         }
     }
 
     fn generate_knight_danger_zone(&self, coordinate: Coordinate) -> Option<Bitboard> {
         match self.pieces[coordinate as usize] {
-            Some(Piece(_, PieceKind::Knight)) => {
-                let mut result = Bitboard::empty();
-
-                let mut try_register_danger = |dx: i8, dy: i8| {
-                    if let Ok(end) = coordinate.try_move(dx, dy) {
-                        result.set(end, true);
-                    }
-                };
-
-                try_register_danger(1, 2);
-                try_register_danger(2, 1);
-                try_register_danger(2, -1);
-                try_register_danger(1, -2);
-                try_register_danger(-1, -2);
-                try_register_danger(-2, -1);
-                try_register_danger(-2, 1);
-                try_register_danger(-1, 2);
-
-                Some(result)
-            }
+            Some(Piece(_, PieceKind::Knight)) => Some(attacks::knight(coordinate)),
             _ => None,
         }
     }
 
     fn generate_bishop_danger_zone(&self, coordinate: Coordinate) -> Option<Bitboard> {
         match self.pieces[coordinate as usize] {
-            Some(Piece(_, PieceKind::Bishop)) => {
-                let mut result = Bitboard::empty();
-
-                self.walk_dangerously(&mut result, coordinate, 1, 1);
-                self.walk_dangerously(&mut result, coordinate, 1, -1);
-                self.walk_dangerously(&mut result, coordinate, -1, -1);
-                self.walk_dangerously(&mut result, coordinate, -1, 1);
-
-                Some(result)
+            Some(Piece(color, PieceKind::Bishop)) => {
+                Some(attacks::bishop(coordinate, self.danger_occupancy(color)))
             }
             _ => None,
         }
@@ -1410,15 +1759,8 @@ impl Board {
 
     fn generate_rook_danger_zone(&self, coordinate: Coordinate) -> Option<Bitboard> {
         match self.pieces[coordinate as usize] {
-            Some(Piece(_, PieceKind::Rook)) => {
-                let mut result = Bitboard::empty();
-
-                self.walk_dangerously(&mut result, coordinate, 0, 1);
-                self.walk_dangerously(&mut result, coordinate, 1, 0);
-                self.walk_dangerously(&mut result, coordinate, 0, -1);
-                self.walk_dangerously(&mut result, coordinate, -1, 0);
-
-                Some(result)
+            Some(Piece(color, PieceKind::Rook)) => {
+                Some(attacks::rook(coordinate, self.danger_occupancy(color)))
             }
             _ => None,
         }
@@ -1426,19 +1768,8 @@ impl Board {
 
     fn generate_queen_danger_zone(&self, coordinate: Coordinate) -> Option<Bitboard> {
         match self.pieces[coordinate as usize] {
-            Some(Piece(_, PieceKind::Queen)) => {
-                let mut result = Bitboard::empty();
-
-                self.walk_dangerously(&mut result, coordinate, 0, 1);
-                self.walk_dangerously(&mut result, coordinate, 1, 1);
-                self.walk_dangerously(&mut result, coordinate, 1, 0);
-                self.walk_dangerously(&mut result, coordinate, 1, -1);
-                self.walk_dangerously(&mut result, coordinate, 0, -1);
-                self.walk_dangerously(&mut result, coordinate, -1, -1);
-                self.walk_dangerously(&mut result, coordinate, -1, 0);
-                self.walk_dangerously(&mut result, coordinate, -1, 1);
-
-                Some(result)
+            Some(Piece(color, PieceKind::Queen)) => {
+                Some(attacks::queen(coordinate, self.danger_occupancy(color)))
             }
             _ => None,
         }
@@ -1446,26 +1777,7 @@ impl Board {
 
     fn generate_king_danger_zone(&self, coordinate: Coordinate) -> Option<Bitboard> {
         match self.pieces[coordinate as usize] {
-            Some(Piece(_, PieceKind::King)) => {
-                let mut result = Bitboard::empty();
-
-                let mut try_register_danger = |dx: i8, dy: i8| {
-                    if let Ok(end) = coordinate.try_move(dx, dy) {
-                        result.set(end, true);
-                    }
-                };
-
-                try_register_danger(0, 1);
-                try_register_danger(1, 1);
-                try_register_danger(1, 0);
-                try_register_danger(1, -1);
-                try_register_danger(0, -1);
-                try_register_danger(-1, -1);
-                try_register_danger(-1, 0);
-                try_register_danger(-1, 1);
-
-                Some(result)
-            }
+            Some(Piece(_, PieceKind::King)) => Some(attacks::king(coordinate)),
             _ => None,
         }
     }
@@ -1473,59 +1785,26 @@ impl Board {
     fn generate_danger_zone(&self, color: Color) -> Bitboard {
         let mut result = Bitboard::empty();
 
-        for y in 0..BOARD_HEIGHT {
-            for x in 0..BOARD_WIDTH {
-                let coordinate = Coordinate::try_from(y * BOARD_WIDTH + x)
-                    .expect("The given index should always be within the board's length.");
-
-                if let Some(piece) = self.pieces[coordinate as usize] {
-                    if piece.0 != color {
-                        continue;
-                    }
-
-                    result |= match piece.1 {
-                        PieceKind::Pawn => self
-                            .generate_pawn_danger_zone(coordinate)
-                            .unwrap_or_default(),
-                        PieceKind::Knight => self
-                            .generate_knight_danger_zone(coordinate)
-                            .unwrap_or_default(),
-                        PieceKind::Bishop => self
-                            .generate_bishop_danger_zone(coordinate)
-                            .unwrap_or_default(),
-                        PieceKind::Rook => self
-                            .generate_rook_danger_zone(coordinate)
-                            .unwrap_or_default(),
-                        PieceKind::Queen => self
-                            .generate_queen_danger_zone(coordinate)
-                            .unwrap_or_default(),
-                        PieceKind::King => self
-                            .generate_king_danger_zone(coordinate)
-                            .unwrap_or_default(),
-                    }
-                }
+        for coordinate in self.occupancy_of(color) {
+            let Piece(_, kind) = self.pieces[coordinate as usize]
+                .expect("An occupied Coordinate should always index a Some Piece.");
+
+            result |= match kind {
+                PieceKind::Pawn => self.generate_pawn_danger_zone(coordinate),
+                PieceKind::Knight => self.generate_knight_danger_zone(coordinate),
+                PieceKind::Bishop => self.generate_bishop_danger_zone(coordinate),
+                PieceKind::Rook => self.generate_rook_danger_zone(coordinate),
+                PieceKind::Queen => self.generate_queen_danger_zone(coordinate),
+                PieceKind::King => self.generate_king_danger_zone(coordinate),
             }
+            .unwrap_or_default();
         }
 
         result
     }
 
     fn find_king(&self, color: Color) -> Option<Coordinate> {
-        for y in 0..BOARD_HEIGHT {
-            for x in 0..BOARD_WIDTH {
-                let coordinate = Coordinate::try_from(y * BOARD_WIDTH + x)
-                    .expect("The given index should always be within the board's length.");
-
-                match self.pieces[coordinate as usize] {
-                    Some(Piece(temp, PieceKind::King)) if temp == color => {
-                        return Some(coordinate);
-                    }
-                    _ => (),
-                }
-            }
-        }
-
-        None
+        self.find(color, PieceKind::King).into_iter().next()
     }
 }
 
@@ -1539,8 +1818,11 @@ impl<B: Borrow<Placement>> From<B> for Board {
     fn from(value: B) -> Self {
         let value = value.borrow();
 
-        let mut pieces: [Option<Piece>; (BOARD_WIDTH * BOARD_HEIGHT) as usize] =
-            [None; (BOARD_WIDTH * BOARD_HEIGHT) as usize];
+        let mut board = Board {
+            pieces: [None; (BOARD_WIDTH * BOARD_HEIGHT) as usize],
+            colors: [Bitboard::empty(); 2],
+            kinds: [Bitboard::empty(); 6],
+        };
         let ranks = value.0.split('/');
 
         let mut y = 0;
@@ -1552,12 +1834,15 @@ impl<B: Borrow<Placement>> From<B> for Board {
 
             for character in characters {
                 if let Some(delta) = character.to_digit(10) {
-                    x += delta as usize;
+                    x += delta as u8;
 
                     continue;
                 }
 
-                pieces[y * BOARD_WIDTH as usize + x] = Piece::try_from(character).ok();
+                let coordinate = Coordinate::try_from(y * BOARD_WIDTH + x)
+                    .expect("A valid Placement should always stay within the board.");
+
+                board.set(coordinate, Piece::try_from(character).ok());
 
                 x += 1;
             }
@@ -1565,7 +1850,7 @@ impl<B: Borrow<Placement>> From<B> for Board {
             y += 1;
         }
 
-        Board { pieces }
+        board
     }
 }
 
@@ -1577,12 +1862,6 @@ impl Index<Coordinate> for Board {
     }
 }
 
-impl IndexMut<Coordinate> for Board {
-    fn index_mut(&mut self, index: Coordinate) -> &mut Self::Output {
-        &mut self.pieces[index as usize]
-    }
-}
-
 #[derive(Debug, Default, PartialEq, Eq, Copy, Clone)]
 struct Bitboard(u64);
 
@@ -1591,6 +1870,10 @@ impl Bitboard {
         Default::default()
     }
 
+    fn is_empty(&self) -> bool {
+        self.0 == 0
+    }
+
     fn get(&self, coordinate: Coordinate) -> bool {
         ((1 << coordinate as u64) & self.0) != 0
     }
@@ -1603,17 +1886,7 @@ impl Bitboard {
     }
 
     fn population_count(&self) -> usize {
-        // Derived from "Brian Kernighan's way" mentioned here: https://www.chessprogramming.org/Population_Count
-        let mut total = 0;
-        let mut contents = self.0;
-
-        while contents != 0 {
-            contents &= contents - 1;
-
-            total += 1;
-        }
-
-        total as usize
+        self.0.count_ones() as usize
     }
 }
 
@@ -1633,6 +1906,28 @@ impl BitOrAssign for Bitboard {
     }
 }
 
+impl BitAnd for Bitboard {
+    type Output = Bitboard;
+
+    fn bitand(self, rhs: Self) -> Self::Output {
+        Bitboard(self.0 & rhs.0)
+    }
+}
+
+impl BitAndAssign for Bitboard {
+    fn bitand_assign(&mut self, rhs: Self) {
+        self.0 &= rhs.0;
+    }
+}
+
+impl Not for Bitboard {
+    type Output = Bitboard;
+
+    fn not(self) -> Self::Output {
+        Bitboard(!self.0)
+    }
+}
+
 impl From<Vec<Coordinate>> for Bitboard {
     fn from(value: Vec<Coordinate>) -> Self {
         let mut result = Bitboard::empty();
@@ -1645,6 +1940,133 @@ impl From<Vec<Coordinate>> for Bitboard {
     }
 }
 
+/// Yields every Coordinate of a Bitboard, starting from the top left of the board.
+struct BitboardIterator(u64);
+
+impl Iterator for BitboardIterator {
+    type Item = Coordinate;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.0 == 0 {
+            return None;
+        }
+
+        let index = self.0.trailing_zeros();
+
+        // Clear the least significant bit.
+        self.0 &= self.0 - 1;
+
+        Some(
+            Coordinate::try_from(index as u8)
+                .expect("The given index should always be within the board's length."),
+        )
+    }
+}
+
+impl IntoIterator for Bitboard {
+    type Item = Coordinate;
+    type IntoIter = BitboardIterator;
+
+    fn into_iter(self) -> Self::IntoIter {
+        BitboardIterator(self.0)
+    }
+}
+
+/// Random numbers used to incrementally hash a State.
+///
+/// Learn more about Zobrist hashing here: https://www.chessprogramming.org/Zobrist_Hashing
+struct ZobristKeys {
+    pieces: [[u64; (BOARD_WIDTH * BOARD_HEIGHT) as usize]; 12],
+    side_to_move: u64,
+    castling_ability: [u64; 4],
+    en_passant_file: [u64; BOARD_WIDTH as usize],
+}
+
+impl ZobristKeys {
+    const fn new() -> Self {
+        // A SplitMix64 generator with a fixed seed; the keys need to be the same every time. The
+        // seed is threaded through by value, so that this stays callable in a `const fn`.
+        const fn next(seed: u64) -> (u64, u64) {
+            let seed = seed.wrapping_add(0x9E37_79B9_7F4A_7C15);
+
+            let mut z = seed;
+            z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+            z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+
+            (seed, z ^ (z >> 31))
+        }
+
+        let mut seed = 0x5045_5343_4144_4F00;
+
+        let mut pieces = [[0; (BOARD_WIDTH * BOARD_HEIGHT) as usize]; 12];
+        let mut castling_ability = [0; 4];
+        let mut en_passant_file = [0; BOARD_WIDTH as usize];
+
+        let mut i = 0;
+
+        while i < pieces.len() {
+            let mut j = 0;
+
+            while j < pieces[i].len() {
+                (seed, pieces[i][j]) = next(seed);
+                j += 1;
+            }
+
+            i += 1;
+        }
+
+        let side_to_move;
+        (seed, side_to_move) = next(seed);
+
+        let mut i = 0;
+
+        while i < castling_ability.len() {
+            (seed, castling_ability[i]) = next(seed);
+            i += 1;
+        }
+
+        let mut i = 0;
+
+        while i < en_passant_file.len() {
+            (seed, en_passant_file[i]) = next(seed);
+            i += 1;
+        }
+
+        ZobristKeys {
+            pieces,
+            side_to_move,
+            castling_ability,
+            en_passant_file,
+        }
+    }
+
+    fn piece(&self, piece: Piece, coordinate: Coordinate) -> u64 {
+        self.pieces[piece.0 as usize * 6 + piece.1 as usize][coordinate as usize]
+    }
+
+    fn castling_ability(&self, castling_ability: Option<CastlingAbility>) -> u64 {
+        let bits = castling_ability.map(|ability| ability.bits()).unwrap_or(0);
+
+        let mut result = 0;
+
+        for (i, key) in self.castling_ability.iter().enumerate() {
+            if bits & (1 << i) != 0 {
+                result ^= key;
+            }
+        }
+
+        result
+    }
+
+    fn en_passant_target(&self, en_passant_target: Option<Coordinate>) -> u64 {
+        en_passant_target
+            .map(|target| self.en_passant_file[target.x() as usize])
+            .unwrap_or(0)
+    }
+}
+
+static ZOBRIST: ZobristKeys = ZobristKeys::new();
+
 #[derive(Debug, PartialEq, Eq, Copy, Clone)]
 enum KingSafety {
     Safe,
@@ -1653,13 +2075,73 @@ enum KingSafety {
     Stalemate,
 }
 
+/// The most moves that are ever generated for a single position (the most known to be possible is
+/// 218).
+const MAX_MOVES: usize = 256;
+
+/// A list of moves that lives entirely on the stack.
+#[derive(Debug, Clone, Copy)]
+struct MoveList {
+    moves: [Move; MAX_MOVES],
+    length: usize,
+}
+
+impl MoveList {
+    fn new() -> Self {
+        MoveList {
+            moves: [Move(0); MAX_MOVES],
+            length: 0,
+        }
+    }
+
+    fn push(&mut self, mv: Move) {
+        self.moves[self.length] = mv;
+        self.length += 1;
+    }
+}
+
+impl Deref for MoveList {
+    type Target = [Move];
+
+    fn deref(&self) -> &Self::Target {
+        &self.moves[..self.length]
+    }
+}
+
+impl DerefMut for MoveList {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.moves[..self.length]
+    }
+}
+
+/// Which kind of moves should be generated.
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+enum MoveFilter {
+    All,
+    /// Every move that captures a piece (including en passant).
+    Captures,
+    /// Every move that does not capture a piece (including castling and promoting by advancing).
+    Quiets,
+}
+
 struct Analysis {
-    moves: Vec<Option<Vec<Lan>>>,
+    moves: MoveList,
     danger_zone: Bitboard,
-    king_location: Coordinate,
     king_safety: KingSafety,
 }
 
+/// Whether or not a game is over, and why.
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub enum Outcome {
+    Ongoing,
+    /// The given side won the game.
+    Checkmate(Color),
+    Stalemate,
+    FiftyMove,
+    InsufficientMaterial,
+    Repetition,
+}
+
 #[derive(Debug, PartialEq, Eq, Clone, Copy)]
 pub struct State {
     board: Board,
@@ -1668,35 +2150,319 @@ pub struct State {
     en_passant_target: Option<Coordinate>,
     half_moves: usize,
     full_moves: usize,
+    /// The Zobrist hash of the position.
+    hash: u64,
 }
 
+#[derive(Debug, PartialEq, Eq)]
 struct StateUndoer {
     move_undoer: MoveUndoer,
     castling_ability: Option<CastlingAbility>,
     en_passant_target: Option<Coordinate>,
     half_moves: usize,
+    hash: u64,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+struct NullMoveUndoer {
+    en_passant_target: Option<Coordinate>,
+    half_moves: usize,
+    hash: u64,
 }
 
+/// Everything needed to take back a move that was played on a State.
+#[derive(Debug, PartialEq, Eq)]
+pub struct Undo(StateUndoer);
+
 impl Default for State {
     fn default() -> Self {
-        State {
-            board: Default::default(),
-            side_to_move: Color::White,
-            castling_ability: Some(
-                CastlingAbility::WHITE_KINGSIDE
-                    | CastlingAbility::WHITE_QUEENSIDE
-                    | CastlingAbility::BLACK_KINGSIDE
-                    | CastlingAbility::BLACK_QUEENSIDE,
-            ),
-            en_passant_target: None,
-            half_moves: 0,
-            full_moves: 1,
-        }
+        State::from(Fen::default())
     }
 }
 
 impl State {
-    fn make_move(&mut self, lan: Lan) -> Result<StateUndoer, ChessError> {
+    /// Returns the Zobrist hash of the position.
+    pub fn hash(&self) -> u64 {
+        self.hash
+    }
+
+    /// Returns every legal move the side to move can make.
+    pub fn legal_moves(&self) -> Vec<Lan> {
+        self.analyze(self.side_to_move, MoveFilter::All)
+            .moves
+            .iter()
+            .map(|&mv| Lan::from(mv))
+            .collect()
+    }
+
+    /// Returns every legal move the piece on the given Coordinate can make. Nothing is returned if
+    /// the Coordinate is empty or the piece does not belong to the side to move.
+    pub fn legal_moves_from(&self, coordinate: Coordinate) -> Vec<Lan> {
+        self.analyze(self.side_to_move, MoveFilter::All)
+            .moves
+            .iter()
+            .filter(|mv| mv.start() == coordinate)
+            .map(|&mv| Lan::from(mv))
+            .collect()
+    }
+
+    /// Formats the given legal move in Standard Algebraic Notation (e.g. e4, Nbd2, O-O, exd8=Q+).
+    pub fn san(&self, lan: Lan) -> Result<String, ChessError> {
+        let moves = self.legal_moves();
+
+        if !moves.contains(&lan) {
+            return Err(ChessError(
+                ChessErrorKind::Other,
+                "The given move is not legal.",
+            ));
+        }
+
+        let mut result = self.san_without_suffix(lan, &moves);
+
+        let mut next = *self;
+
+        next.make_move(self.board.encode(lan)?)?;
+
+        match next.analyze(next.side_to_move, MoveFilter::All).king_safety {
+            KingSafety::Checkmate => result.push('#'),
+            KingSafety::Check => result.push('+'),
+            _ => (),
+        }
+
+        Ok(result)
+    }
+
+    /// Resolves a move in Standard Algebraic Notation against the current legal moves.
+    ///
+    /// Check and mate suffixes, as well as annotations such as `!?` and `e.p.`, are optional.
+    pub fn parse_san(&self, san: &str) -> Result<Lan, ChessError> {
+        let san = san.trim().trim_end_matches(['+', '#', '!', '?']);
+        let san = san.strip_suffix("e.p.").unwrap_or(san).trim_end();
+        let san = san.trim_end_matches(['+', '#']);
+        // Castling is sometimes written with zeros, and promotions without an equals sign.
+        let mut san = san.replace('0', "O").replace('=', "");
+
+        // Promotions are sometimes written with a lowercase piece (e.g. e8=q).
+        if let [.., rank, piece] = san.as_bytes() {
+            if rank.is_ascii_digit() && b"qrbn".contains(piece) {
+                let piece = piece.to_ascii_uppercase() as char;
+
+                san.pop();
+                san.push(piece);
+            }
+        }
+
+        let moves = self.legal_moves();
+        let mut matches = moves
+            .iter()
+            .filter(|&&lan| self.san_without_suffix(lan, &moves).replace('=', "") == san);
+
+        match (matches.next(), matches.next()) {
+            (Some(&lan), None) => Ok(lan),
+            _ => Err(ChessError(
+                ChessErrorKind::InvalidString,
+                "The given SAN string does not describe exactly one legal move.",
+            )),
+        }
+    }
+
+    fn san_without_suffix(&self, lan: Lan, moves: &[Lan]) -> String {
+        let Piece(_, kind) = self.board[lan.start]
+            .expect("A legal move should always start on a Coordinate that is occupied.");
+
+        let file = |coordinate: Coordinate| (b'a' + coordinate.x()) as char;
+        let rank = |coordinate: Coordinate| (b'0' + BOARD_HEIGHT - coordinate.y()) as char;
+
+        if kind == PieceKind::King && lan.start.x().abs_diff(lan.end.x()) == 2 {
+            return match lan.end.x() > lan.start.x() {
+                true => String::from("O-O"),
+                false => String::from("O-O-O"),
+            };
+        }
+
+        let mut result = String::new();
+
+        // Pawns are the only pieces that can change files without capturing, except en passant.
+        let capture = self.board[lan.end].is_some()
+            || (kind == PieceKind::Pawn && lan.start.x() != lan.end.x());
+
+        if kind == PieceKind::Pawn {
+            if capture {
+                result.push(file(lan.start));
+            }
+        } else {
+            result.push_str(&<&str>::from(kind).to_ascii_uppercase());
+
+            // Other pieces of the same kind that can also move to the same Coordinate.
+            let others = moves
+                .iter()
+                .filter(|other| other.end == lan.end && other.start != lan.start)
+                .filter(|other| self.board[other.start].map(|piece| piece.1) == Some(kind))
+                .collect::<Vec<&Lan>>();
+
+            if !others.is_empty() {
+                if others.iter().all(|other| other.start.x() != lan.start.x()) {
+                    result.push(file(lan.start));
+                } else if others.iter().all(|other| other.start.y() != lan.start.y()) {
+                    result.push(rank(lan.start));
+                } else {
+                    result.push(file(lan.start));
+                    result.push(rank(lan.start));
+                }
+            }
+        }
+
+        if capture {
+            result.push('x');
+        }
+
+        result.push_str(&lan.end.to_string());
+
+        if let Some(promotion) = lan.promotion {
+            result.push('=');
+            result.push_str(&<&str>::from(promotion).to_ascii_uppercase());
+        }
+
+        result
+    }
+
+    /// Plays the given move if it is legal.
+    pub fn play(&mut self, lan: Lan) -> Result<Undo, ChessError> {
+        let mv = self
+            .analyze(self.side_to_move, MoveFilter::All)
+            .moves
+            .iter()
+            .find(|&&mv| Lan::from(mv) == lan)
+            .copied()
+            .ok_or(ChessError(
+                ChessErrorKind::Other,
+                "The given move is not legal.",
+            ))?;
+
+        self.make_move(mv).map(Undo)
+    }
+
+    /// Plays the given move without checking whether or not it is legal.
+    ///
+    /// This is much faster than `play`, but it is up to the caller to only ever pass legal moves
+    /// (e.g. moves from `legal_moves`); the State is left in an invalid position otherwise. Moves
+    /// that no piece could ever make (e.g. a king moving two files away from its home square) are
+    /// still rejected.
+    pub fn play_unchecked(&mut self, lan: Lan) -> Result<Undo, ChessError> {
+        let mv = self.board.encode(lan)?;
+
+        self.make_move(mv).map(Undo)
+    }
+
+    /// Takes back the move that produced the given Undo.
+    pub fn undo(&mut self, undo: Undo) {
+        self.unmake_move(undo.0);
+    }
+
+    /// Works out how much material the side to move wins (or loses) by playing the given capture,
+    /// assuming that both sides keep recapturing on the same Coordinate with their least valuable
+    /// piece for as long as it pays off.
+    ///
+    /// The move only needs to be pseudo-legal; pins are not taken into account.
+    pub fn see(&self, lan: Lan) -> Result<i16, ChessError> {
+        let mv = self.board.encode(lan)?;
+
+        Ok(self.static_exchange(mv))
+    }
+
+    /// Determines whether or not the game has ended.
+    ///
+    /// `history` should contain the hash of every State that was played before this one, and is
+    /// only used to detect threefold repetition; an empty slice is fine otherwise.
+    pub fn outcome(&self, history: &[u64]) -> Outcome {
+        match self.analyze(self.side_to_move, MoveFilter::All).king_safety {
+            KingSafety::Checkmate => return Outcome::Checkmate(self.side_to_move.opponent()),
+            KingSafety::Stalemate => return Outcome::Stalemate,
+            _ => (),
+        }
+
+        if self.has_insufficient_material() {
+            return Outcome::InsufficientMaterial;
+        }
+
+        if self.is_fifty_move_draw() {
+            return Outcome::FiftyMove;
+        }
+
+        if Engine::is_repetition(self, history, history.len()) {
+            return Outcome::Repetition;
+        }
+
+        Outcome::Ongoing
+    }
+
+    /// Returns whether or not a draw can be claimed because fifty moves have been played without a
+    /// capture or pawn move.
+    ///
+    /// Note that checkmate takes precedence over the fifty-move rule.
+    pub fn is_fifty_move_draw(&self) -> bool {
+        self.half_moves >= FIFTY_MOVE_RULE
+    }
+
+    /// Returns whether or not neither side has enough material left to ever checkmate the other.
+    pub fn has_insufficient_material(&self) -> bool {
+        let mut knights = 0;
+        let mut light_bishops = 0;
+        let mut dark_bishops = 0;
+
+        for (index, piece) in self.board.pieces.iter().enumerate() {
+            match piece {
+                Some(Piece(_, PieceKind::Pawn | PieceKind::Rook | PieceKind::Queen)) => {
+                    return false;
+                }
+                Some(Piece(_, PieceKind::Knight)) => knights += 1,
+                Some(Piece(_, PieceKind::Bishop)) => {
+                    let x = index as u8 % BOARD_WIDTH;
+                    let y = index as u8 / BOARD_WIDTH;
+
+                    if (x + y) % 2 == 0 {
+                        light_bishops += 1;
+                    } else {
+                        dark_bishops += 1;
+                    }
+                }
+                _ => (),
+            }
+        }
+
+        match (knights, light_bishops, dark_bishops) {
+            // A lone knight or bishop (e.g. KN vs K, KB vs K).
+            (0, 0, 0) | (1, 0, 0) | (0, 1, 0) | (0, 0, 1) => true,
+            // Bishops that all share the same square color can never attack the other color.
+            (0, _, 0) | (0, 0, _) => true,
+            _ => false,
+        }
+    }
+
+    /// Hashes the entire position from scratch.
+    fn zobrist(&self) -> u64 {
+        let mut hash = 0;
+
+        for (index, piece) in self.board.pieces.iter().enumerate() {
+            if let Some(piece) = piece {
+                let coordinate = Coordinate::try_from(index as u8)
+                    .expect("The given index should always be within the board's length.");
+
+                hash ^= ZOBRIST.piece(*piece, coordinate);
+            }
+        }
+
+        if self.side_to_move == Color::Black {
+            hash ^= ZOBRIST.side_to_move;
+        }
+
+        hash ^= ZOBRIST.castling_ability(self.castling_ability);
+        hash ^= ZOBRIST.en_passant_target(self.en_passant_target);
+
+        hash
+    }
+
+    fn make_move(&mut self, mv: Move) -> Result<StateUndoer, ChessError> {
         let current_side = self.side_to_move;
         let opponent = self.side_to_move.opponent();
 
@@ -1704,15 +2470,16 @@ impl State {
         let castling_ability = self.castling_ability;
         let en_passant_target = self.en_passant_target;
         let half_moves = self.half_moves;
+        let hash = self.hash;
 
-        let piece = self.board[lan.start].ok_or(ChessError(
+        let piece = self.board[mv.start()].ok_or(ChessError(
             ChessErrorKind::TargetIsNone,
             "Cannot move a piece that does not exist.",
         ))?;
-        let target = self.board[lan.end];
+        let target = self.board[mv.end()];
 
-        let capture = target.is_some();
-        let dy = lan.end.y() as i8 - lan.start.y() as i8;
+        let capture = mv.is_capture();
+        let dy = mv.end().y() as i8 - mv.start().y() as i8;
 
         // Toggle the current side.
         self.side_to_move = opponent;
@@ -1788,13 +2555,13 @@ impl State {
 
         // Make sure that moving a rook affects the king's ability to castle.
         if piece.1 == PieceKind::Rook {
-            if lan.start == king_side_index {
+            if mv.start() == king_side_index {
                 if let Some(ability) = self.castling_ability {
                     if !(ability & king_side).is_empty() {
                         self.castling_ability = Some(ability ^ king_side);
                     }
                 }
-            } else if lan.start == queen_side_index {
+            } else if mv.start() == queen_side_index {
                 if let Some(ability) = self.castling_ability {
                     if !(ability & queen_side).is_empty() {
                         self.castling_ability = Some(ability ^ queen_side);
@@ -1817,13 +2584,13 @@ impl State {
 
         // Capturing a rook on either corner should disable castling on that side.
         if let Some(Piece(_, PieceKind::Rook)) = target {
-            if lan.end == king_side_index {
+            if mv.end() == king_side_index {
                 if let Some(ability) = self.castling_ability {
                     if (ability & king_side) != CastlingAbility::empty() {
                         self.castling_ability = Some(ability ^ king_side);
                     }
                 }
-            } else if lan.end == queen_side_index {
+            } else if mv.end() == queen_side_index {
                 if let Some(ability) = self.castling_ability {
                     if (ability & queen_side) != CastlingAbility::empty() {
                         self.castling_ability = Some(ability ^ queen_side);
@@ -1835,17 +2602,17 @@ impl State {
         self.en_passant_target = None;
 
         // Handle setting up a potential en passant.
-        if dy.abs() == 2 && piece.1 == PieceKind::Pawn {
+        if mv.flag() == MoveFlag::DoublePush {
             let direction = -dy.signum();
-            let potential_en_passant_target = lan
-                .start
+            let potential_en_passant_target = mv
+                .start()
                 .try_move(0, direction)
                 .expect("A pawn that moved two squares should be able to move one.");
 
             // Only enable en_passant_target if an enemy pawn is in position to capture en passant.
             let mut pawns = 0;
 
-            if let Ok(coordinate) = lan.end.try_move(-1, 0) {
+            if let Ok(coordinate) = mv.end().try_move(-1, 0) {
                 match self.board[coordinate] {
                     Some(Piece(color, PieceKind::Pawn)) if color == opponent => {
                         self.en_passant_target = Some(potential_en_passant_target);
@@ -1854,7 +2621,7 @@ impl State {
                     _ => (),
                 }
             }
-            if let Ok(coordinate) = lan.end.try_move(1, 0) {
+            if let Ok(coordinate) = mv.end().try_move(1, 0) {
                 match self.board[coordinate] {
                     Some(Piece(color, PieceKind::Pawn)) if color == opponent => {
                         self.en_passant_target = Some(potential_en_passant_target);
@@ -1892,7 +2659,7 @@ impl State {
 
                 if let Some(kings_coordinate) = kings_coordinate {
                     // Remove pawn from `rank` (assume opponent took en passant).
-                    let x = lan.end.x();
+                    let x = mv.end().x();
 
                     if x < BOARD_WIDTH - 1 {
                         let index = x as usize + 1;
@@ -1961,15 +2728,50 @@ impl State {
         }
 
         // Move the piece.
-        let move_undoer = self.board.make_move(lan)?;
+        let move_undoer = self.board.make_move(mv)?;
 
-        Ok(StateUndoer {
-            move_undoer,
-            castling_ability,
-            en_passant_target,
-            half_moves,
-        })
-    }
+        // Incrementally update the hash with everything that changed.
+        self.hash ^= ZOBRIST.side_to_move;
+        self.hash ^= ZOBRIST.castling_ability(castling_ability)
+            ^ ZOBRIST.castling_ability(self.castling_ability);
+        self.hash ^= ZOBRIST.en_passant_target(en_passant_target)
+            ^ ZOBRIST.en_passant_target(self.en_passant_target);
+
+        self.hash ^= ZOBRIST.piece(piece, mv.start());
+
+        if let Some(target) = target {
+            self.hash ^= ZOBRIST.piece(target, mv.end());
+        }
+
+        // Promotions are accounted for by hashing whatever ended up on the end Coordinate.
+        let moved =
+            self.board[mv.end()].expect("The piece should have moved to the end Coordinate.");
+
+        self.hash ^= ZOBRIST.piece(moved, mv.end());
+
+        match mv.flag() {
+            MoveFlag::EnPassant => {
+                let coordinate = Board::en_passant_capture(mv);
+
+                self.hash ^= ZOBRIST.piece(Piece(opponent, PieceKind::Pawn), coordinate);
+            }
+            MoveFlag::KingCastle | MoveFlag::QueenCastle => {
+                let (rook_start, rook_end) = Board::castling_rook(mv.end());
+                let rook = Piece(current_side, PieceKind::Rook);
+
+                self.hash ^= ZOBRIST.piece(rook, rook_start) ^ ZOBRIST.piece(rook, rook_end);
+            }
+            _ => (),
+        }
+
+        Ok(StateUndoer {
+            move_undoer,
+            castling_ability,
+            en_passant_target,
+            half_moves,
+            hash,
+        })
+    }
 
     fn unmake_move(&mut self, undoer: StateUndoer) {
         self.board.unmake_move(undoer.move_undoer);
@@ -1978,847 +2780,457 @@ impl State {
         self.castling_ability = undoer.castling_ability;
         self.en_passant_target = undoer.en_passant_target;
         self.half_moves = undoer.half_moves;
+        self.hash = undoer.hash;
 
         if self.side_to_move == Color::Black {
             self.full_moves -= 1;
         }
     }
 
-    fn walk(&self, moves: &mut Vec<Lan>, start: Coordinate, opponent: Color, dx: i8, dy: i8) {
-        let size = BOARD_WIDTH.max(BOARD_HEIGHT) as i8;
+    /// Whether or not the king of the side to move is under attack.
+    fn is_in_check(&self) -> bool {
+        let king = self
+            .board
+            .find_king(self.side_to_move)
+            .expect("A valid State must always have one white and black king.");
+        let opponent = self.board.occupancy_of(self.side_to_move.opponent());
+
+        !(self.board.attackers_to(king, self.board.occupancy()) & opponent).is_empty()
+    }
 
-        let mut push_move = |end: Coordinate| {
-            moves.push(Lan {
-                start,
-                end,
-                promotion: None,
-            });
+    /// Passes the turn to the opponent without moving a piece.
+    ///
+    /// Passing is treated as irreversible, so repetitions are never detected across it.
+    fn make_null_move(&mut self) -> NullMoveUndoer {
+        let undoer = NullMoveUndoer {
+            en_passant_target: self.en_passant_target,
+            half_moves: self.half_moves,
+            hash: self.hash,
         };
 
-        for i in 1..size {
-            if let Ok(end) = start.try_move(i * dx, i * dy) {
-                match self.board[end] {
-                    Some(Piece(color, _)) => {
-                        if color == opponent {
-                            push_move(end);
-                        }
+        self.hash ^= ZOBRIST.side_to_move;
+        self.hash ^= ZOBRIST.en_passant_target(self.en_passant_target);
 
-                        break;
-                    }
-                    None => push_move(end),
-                }
-            } else {
-                break;
-            }
+        if self.side_to_move == Color::Black {
+            self.full_moves += 1;
         }
-    }
-
-    fn generate_pseudo_legal_pawn_moves(&self, start: Coordinate) -> Vec<Lan> {
-        let mut moves = Vec::with_capacity(4);
 
-        let piece = self.board[start];
+        self.side_to_move = self.side_to_move.opponent();
+        self.en_passant_target = None;
+        self.half_moves = 0;
 
-        let mut register_move = |end: Coordinate| {
-            const PROMOTIONS: [PieceKind; 4] = [
-                PieceKind::Knight,
-                PieceKind::Bishop,
-                PieceKind::Rook,
-                PieceKind::Queen,
-            ];
+        undoer
+    }
 
-            if end.y() == 0 || end.y() == BOARD_HEIGHT - 1 {
-                for kind in PROMOTIONS {
-                    moves.push(Lan {
-                        start,
-                        end,
-                        promotion: Some(kind),
-                    });
-                }
-            } else {
-                moves.push(Lan {
-                    start,
-                    end,
-                    promotion: None,
-                });
-            }
-        };
+    fn unmake_null_move(&mut self, undoer: NullMoveUndoer) {
+        self.side_to_move = self.side_to_move.opponent();
+        self.en_passant_target = undoer.en_passant_target;
+        self.half_moves = undoer.half_moves;
+        self.hash = undoer.hash;
 
-        match piece {
-            Some(Piece(Color::White, PieceKind::Pawn)) => {
-                // Handle advancing one square.
-                if let Ok(end) = start.try_move(0, 1) {
-                    if self.board[end].is_none() {
-                        register_move(end);
-                    }
-                }
+        if self.side_to_move == Color::Black {
+            self.full_moves -= 1;
+        }
+    }
 
-                // Handle advancing two squares (if the pawn has not moved before).
-                if start.y() == BOARD_HEIGHT - 2 {
-                    if let (Ok(prerequisite), Ok(end)) =
-                        (start.try_move(0, 1), start.try_move(0, 2))
-                    {
-                        if let (None, None) = (self.board[prerequisite], self.board[end]) {
-                            register_move(end);
-                        }
-                    }
-                }
+    /// Static exchange evaluation; see `see`.
+    ///
+    /// Learn more about static exchange evaluation here: https://www.chessprogramming.org/SEE_-_The_Swap_Algorithm
+    fn static_exchange(&self, mv: Move) -> i16 {
+        const ORDER: [PieceKind; 6] = [
+            PieceKind::Pawn,
+            PieceKind::Knight,
+            PieceKind::Bishop,
+            PieceKind::Rook,
+            PieceKind::Queen,
+            PieceKind::King,
+        ];
+
+        let target = mv.end();
+        let Piece(color, kind) = match self.board[mv.start()] {
+            Some(piece) => piece,
+            None => return 0,
+        };
 
-                // Handle capturing to the top left.
-                if let Ok(end) = start.try_move(-1, 1) {
-                    if let Some(Piece(Color::Black, _)) = self.board[end] {
-                        register_move(end);
-                    }
-                }
+        let mut occupancy = self.board.occupancy();
+        // The material each side has won after every capture in the sequence.
+        let mut gain = [0; 32];
 
-                // Handle capturing to the top right.
-                if let Ok(end) = start.try_move(1, 1) {
-                    if let Some(Piece(Color::Black, _)) = self.board[end] {
-                        register_move(end);
-                    }
-                }
+        gain[0] = match mv.flag() {
+            MoveFlag::EnPassant => {
+                occupancy.set(Board::en_passant_capture(mv), false);
 
-                // Handle en passant.
-                if start.y() == 3 {
-                    if let Some(en_passant_target) = self.en_passant_target {
-                        match start.try_move(-1, 1) {
-                            Ok(end) if end == en_passant_target => register_move(end),
-                            _ => (),
-                        }
-                        match start.try_move(1, 1) {
-                            Ok(end) if end == en_passant_target => register_move(end),
-                            _ => (),
-                        }
-                    }
-                }
+                PieceKind::Pawn.value()
             }
-            Some(Piece(Color::Black, PieceKind::Pawn)) => {
-                // Handle advancing one square.
-                if let Ok(end) = start.try_move(0, -1) {
-                    if self.board[end].is_none() {
-                        register_move(end);
-                    }
-                }
-
-                // Handle advancing two squares (if the pawn has not moved before).
-                if start.y() == 1 {
-                    if let (Ok(prerequisite), Ok(end)) =
-                        (start.try_move(0, -1), start.try_move(0, -2))
-                    {
-                        if let (None, None) = (self.board[prerequisite], self.board[end]) {
-                            register_move(end);
-                        }
-                    }
-                }
+            _ => self.board[target].map_or(0, |Piece(_, kind)| kind.value()),
+        };
 
-                // Handle capturing to the bottom left.
-                if let Ok(end) = start.try_move(-1, -1) {
-                    if let Some(Piece(Color::White, _)) = self.board[end] {
-                        register_move(end);
-                    }
-                }
+        // The value of the piece that currently stands on the target, and would be captured next.
+        let mut standing = match mv.promotion() {
+            Some(promotion) => {
+                gain[0] += promotion.value() - PieceKind::Pawn.value();
 
-                // Handle capturing to the bottom right.
-                if let Ok(end) = start.try_move(1, -1) {
-                    if let Some(Piece(Color::White, _)) = self.board[end] {
-                        register_move(end);
-                    }
-                }
-                {}
-                // Handle en passant.
-                if start.y() == BOARD_HEIGHT - 3 - 1 {
-                    if let Some(en_passant_target) = self.en_passant_target {
-                        match start.try_move(-1, -1) {
-                            Ok(end) if end == en_passant_target => register_move(end),
-                            _ => (),
-                        }
-                        match start.try_move(1, -1) {
-                            Ok(end) if end == en_passant_target => register_move(end),
-                            _ => (),
-                        }
-                    }
-                }
+                promotion.value()
             }
-            _ => (),
-        }
+            None => kind.value(),
+        };
 
-        moves
-    }
+        let mut start = mv.start();
+        let mut side = color;
+        let mut depth = 0;
 
-    fn generate_pseudo_legal_knight_moves(&self, start: Coordinate) -> Vec<Lan> {
-        let mut moves = Vec::with_capacity(8);
+        loop {
+            depth += 1;
 
-        if let Some(Piece(color, PieceKind::Knight)) = self.board[start] {
-            let opponent = color.opponent();
-
-            let mut try_register_move = |dx: i8, dy: i8| {
-                let mut push_move = |end: Coordinate| {
-                    moves.push(Lan {
-                        start,
-                        end,
-                        promotion: None,
-                    });
-                };
+            // Speculatively assume that the piece on the target is captured as well.
+            gain[depth] = standing - gain[depth - 1];
 
-                if let Ok(end) = start.try_move(dx, dy) {
-                    match self.board[end] {
-                        Some(Piece(color, _)) if color == opponent => push_move(end),
-                        None => push_move(end),
-                        _ => (),
-                    }
-                }
-            };
+            // Removing the piece that just captured can uncover a slider behind it.
+            occupancy.set(start, false);
 
-            try_register_move(1, 2);
-            try_register_move(2, 1);
-            try_register_move(2, -1);
-            try_register_move(1, -2);
-            try_register_move(-1, -2);
-            try_register_move(-2, -1);
-            try_register_move(-2, 1);
-            try_register_move(-1, 2);
-        }
+            let attackers = self.board.attackers_to(target, occupancy);
 
-        moves
-    }
+            side = side.opponent();
 
-    fn generate_pseudo_legal_bishop_moves(&self, start: Coordinate) -> Vec<Lan> {
-        let mut moves = Vec::with_capacity(13);
+            let least_valuable = ORDER.iter().find_map(|&kind| {
+                (attackers & self.board.find(side, kind))
+                    .into_iter()
+                    .next()
+                    .map(|coordinate| (coordinate, kind))
+            });
 
-        if let Some(Piece(color, PieceKind::Bishop)) = self.board[start] {
-            let opponent = color.opponent();
+            let (coordinate, kind) = match least_valuable {
+                Some(attacker) => attacker,
+                None => break,
+            };
 
-            self.walk(&mut moves, start, opponent, 1, 1);
-            self.walk(&mut moves, start, opponent, 1, -1);
-            self.walk(&mut moves, start, opponent, -1, -1);
-            self.walk(&mut moves, start, opponent, -1, 1);
-        }
+            // The king can only recapture if nothing would be able to take it back.
+            if kind == PieceKind::King
+                && !(attackers & self.board.occupancy_of(side.opponent())).is_empty()
+            {
+                break;
+            }
 
-        moves
-    }
+            start = coordinate;
+            standing = kind.value();
+        }
 
-    fn generate_pseudo_legal_rook_moves(&self, start: Coordinate) -> Vec<Lan> {
-        let mut moves = Vec::with_capacity(14);
+        // Work backwards; either side can always decline to recapture.
+        loop {
+            depth -= 1;
 
-        if let Some(Piece(color, PieceKind::Rook)) = self.board[start] {
-            let opponent = color.opponent();
+            if depth == 0 {
+                break;
+            }
 
-            self.walk(&mut moves, start, opponent, 0, 1);
-            self.walk(&mut moves, start, opponent, 1, 0);
-            self.walk(&mut moves, start, opponent, 0, -1);
-            self.walk(&mut moves, start, opponent, -1, 0);
+            gain[depth - 1] = -(-gain[depth - 1]).max(gain[depth]);
         }
 
-        moves
+        gain[0]
     }
 
-    fn generate_pseudo_legal_queen_moves(&self, start: Coordinate) -> Vec<Lan> {
-        let mut moves = Vec::with_capacity(27);
+    /// Registers a move from the given start to every Coordinate of the given Bitboard.
+    fn push_moves(&self, moves: &mut MoveList, start: Coordinate, targets: Bitboard) {
+        let occupancy = self.board.occupancy();
 
-        if let Some(Piece(color, PieceKind::Queen)) = self.board[start] {
-            let opponent = color.opponent();
+        for end in targets {
+            let flag = match occupancy.get(end) {
+                true => MoveFlag::Capture,
+                false => MoveFlag::Quiet,
+            };
 
-            self.walk(&mut moves, start, opponent, 0, 1);
-            self.walk(&mut moves, start, opponent, 1, 1);
-            self.walk(&mut moves, start, opponent, 1, 0);
-            self.walk(&mut moves, start, opponent, 1, -1);
-            self.walk(&mut moves, start, opponent, 0, -1);
-            self.walk(&mut moves, start, opponent, -1, -1);
-            self.walk(&mut moves, start, opponent, -1, 0);
-            self.walk(&mut moves, start, opponent, -1, 1);
+            moves.push(Move::new(start, end, flag));
         }
-
-        moves
     }
 
-    fn generate_pseudo_legal_king_moves(&self, start: Coordinate) -> Vec<Lan> {
-        let mut moves = Vec::with_capacity(8);
+    // Each of the following only registers moves that end on one of the given targets, which lets
+    // the caller narrow them down to captures, quiet moves, or legal moves without a second pass.
 
-        let mut push_move = |end: Coordinate| {
-            moves.push(Lan {
-                start,
-                end,
-                promotion: None,
-            });
-        };
+    fn generate_pseudo_legal_pawn_moves(
+        &self,
+        start: Coordinate,
+        targets: Bitboard,
+        moves: &mut MoveList,
+    ) {
+        if let Some(Piece(color, PieceKind::Pawn)) = self.board[start] {
+            let mut register_move = |end: Coordinate, flag: MoveFlag| {
+                const PROMOTIONS: [PieceKind; 4] = [
+                    PieceKind::Knight,
+                    PieceKind::Bishop,
+                    PieceKind::Rook,
+                    PieceKind::Queen,
+                ];
+
+                if !targets.get(end) {
+                    return;
+                }
 
-        if let Some(Piece(color, PieceKind::King)) = self.board[start] {
-            let opponent = color.opponent();
+                if end.y() == 0 || end.y() == BOARD_HEIGHT - 1 {
+                    for kind in PROMOTIONS {
+                        let flag = match flag {
+                            MoveFlag::Capture => MoveFlag::PromotionCapture(kind),
+                            _ => MoveFlag::Promotion(kind),
+                        };
 
-            let mut try_register_move = |dx: i8, dy: i8| {
-                if let Ok(end) = start.try_move(dx, dy) {
-                    match self.board[end] {
-                        Some(Piece(color, _)) if color == opponent => push_move(end),
-                        None => push_move(end),
-                        _ => (),
+                        moves.push(Move::new(start, end, flag));
                     }
+                } else {
+                    moves.push(Move::new(start, end, flag));
                 }
             };
 
-            try_register_move(0, 1);
-            try_register_move(1, 1);
-            try_register_move(1, 0);
-            try_register_move(1, -1);
-            try_register_move(0, -1);
-            try_register_move(-1, -1);
-            try_register_move(-1, 0);
-            try_register_move(-1, 1);
-
-            let king_side = match color {
-                Color::White => CastlingAbility::WHITE_KINGSIDE,
-                Color::Black => CastlingAbility::BLACK_KINGSIDE,
-            };
-            let queen_side = match color {
-                Color::White => CastlingAbility::WHITE_QUEENSIDE,
-                Color::Black => CastlingAbility::BLACK_QUEENSIDE,
+            let (dy, home) = match color {
+                Color::White => (1, BOARD_HEIGHT - 2),
+                Color::Black => (-1, 1),
             };
 
-            if let Some(castling_ability) = self.castling_ability {
-                if (castling_ability & king_side) != CastlingAbility::empty() {
-                    if let (Ok(prerequisite), Ok(end)) =
-                        (start.try_move(1, 0), start.try_move(2, 0))
-                    {
-                        if let (None, None) = (self.board[prerequisite], self.board[end]) {
-                            push_move(end);
-                        }
-                    }
-                }
+            // Handle advancing one square, and then two squares (if the pawn has not moved before).
+            if let Ok(end) = start.try_move(0, dy) {
+                if self.board[end].is_none() {
+                    register_move(end, MoveFlag::Quiet);
 
-                if (castling_ability & queen_side) != CastlingAbility::empty() {
-                    if let (Ok(prerequisite_a), Ok(end), Ok(prerequisite_b)) = (
-                        start.try_move(-1, 0),
-                        start.try_move(-2, 0),
-                        start.try_move(-3, 0),
-                    ) {
-                        if let (None, None, None) = (
-                            self.board[prerequisite_a],
-                            self.board[end],
-                            self.board[prerequisite_b],
-                        ) {
-                            push_move(end);
+                    if start.y() == home {
+                        if let Ok(end) = start.try_move(0, dy * 2) {
+                            if self.board[end].is_none() {
+                                register_move(end, MoveFlag::DoublePush);
+                            }
                         }
                     }
                 }
             }
-        }
-
-        moves
-    }
-
-    fn generate_pseudo_legal_moves(&self, color: Color) -> Vec<Option<Vec<Lan>>> {
-        let mut moves = vec![None; (BOARD_WIDTH * BOARD_HEIGHT) as usize];
 
-        for y in 0..BOARD_HEIGHT {
-            for x in 0..BOARD_WIDTH {
-                let index = (y * BOARD_WIDTH + x) as usize;
-                let start = Coordinate::try_from(index as u8)
-                    .expect("The given index should always be within the board's length.");
-
-                match self.board[start] {
-                    Some(Piece(temp, kind)) if temp == color => {
-                        let move_list = match kind {
-                            PieceKind::Pawn => self.generate_pseudo_legal_pawn_moves(start),
-                            PieceKind::Knight => self.generate_pseudo_legal_knight_moves(start),
-                            PieceKind::Bishop => self.generate_pseudo_legal_bishop_moves(start),
-                            PieceKind::Rook => self.generate_pseudo_legal_rook_moves(start),
-                            PieceKind::Queen => self.generate_pseudo_legal_queen_moves(start),
-                            PieceKind::King => self.generate_pseudo_legal_king_moves(start),
-                        };
+            // Handle capturing diagonally (including en passant).
+            let mut captures = self.board.occupancy_of(color.opponent());
 
-                        moves[index] = Some(move_list);
-                    }
-                    _ => (),
+            if let Some(en_passant_target) = self.en_passant_target {
+                // Only the side to move can capture en passant.
+                if color == self.side_to_move {
+                    captures.set(en_passant_target, true);
                 }
             }
-        }
-
-        moves
-    }
 
-    fn find_pins(&self, coordinate: Coordinate) -> Option<Bitboard> {
-        let target = coordinate;
-        let color = self.board[target]?.0;
-        let opponent = color.opponent();
-
-        let mut result = Bitboard::empty();
-
-        for y in 0..BOARD_HEIGHT {
-            for x in 0..BOARD_WIDTH {
-                let coordinate = Coordinate::try_from(y * BOARD_WIDTH + x)
-                    .expect("The given index should always be within the board's length.");
-
-                if let Some(piece) = self.board[coordinate] {
-                    if piece.0 != opponent {
-                        continue;
-                    }
-
-                    let direction = (|| match piece.1 {
-                        PieceKind::Bishop => {
-                            if coordinate.x() == target.x() || coordinate.y() == target.y() {
-                                return None;
-                            }
-
-                            let difference_x = target.x() as i8 - coordinate.x() as i8;
-                            let difference_y = target.y() as i8 - coordinate.y() as i8;
-
-                            if difference_x.abs() != difference_y.abs() {
-                                return None;
-                            }
-
-                            let x = -(coordinate.x() as i8 - target.x() as i8).signum();
-                            let y = (coordinate.y() as i8 - target.y() as i8).signum();
-
-                            Some((x, y))
-                        }
-                        PieceKind::Rook => {
-                            if coordinate.x() != target.x() && coordinate.y() != target.y() {
-                                return None;
-                            }
-
-                            let x = if coordinate.y() != target.y() {
-                                0
-                            } else {
-                                -(coordinate.x() as i8 - target.x() as i8).signum()
-                            };
-                            let y = if coordinate.x() != target.x() {
-                                0
-                            } else {
-                                (coordinate.y() as i8 - target.y() as i8).signum()
-                            };
-
-                            Some((x, y))
-                        }
-                        PieceKind::Queen => {
-                            let x = if coordinate.y() != target.y() {
-                                0
-                            } else {
-                                -(coordinate.x() as i8 - target.x() as i8).signum()
-                            };
-                            let y = if coordinate.x() != target.x() {
-                                0
-                            } else {
-                                (coordinate.y() as i8 - target.y() as i8).signum()
-                            };
-
-                            if coordinate.x() != target.x() && coordinate.y() != target.y() {
-                                let difference_x = target.x() as i8 - coordinate.x() as i8;
-                                let difference_y = target.y() as i8 - coordinate.y() as i8;
-
-                                if difference_x.abs() != difference_y.abs() {
-                                    return None;
-                                }
-
-                                let x = -(coordinate.x() as i8 - target.x() as i8).signum();
-                                let y = (coordinate.y() as i8 - target.y() as i8).signum();
-
-                                return Some((x, y));
-                            }
-
-                            Some((x, y))
-                        }
-                        _ => None,
-                    })();
-
-                    if let Some((dx, dy)) = direction {
-                        let mut has_line_of_sight = false;
-                        let mut potential_pin: Option<Coordinate> = None;
-
-                        let mut temp = coordinate.try_move(dx, dy);
-
-                        while let Ok(coordinate) = temp {
-                            temp = coordinate.try_move(dx, dy);
-
-                            match self.board[coordinate] {
-                                Some(Piece(temp, _)) if temp == color => {
-                                    if target == coordinate {
-                                        has_line_of_sight = true;
-
-                                        break;
-                                    }
-
-                                    if potential_pin.is_none() {
-                                        potential_pin = Some(coordinate);
-
-                                        continue;
-                                    }
-
-                                    if potential_pin.is_some() {
-                                        break;
-                                    }
-                                }
-                                Some(Piece(color, _)) if color == opponent => {
-                                    break;
-                                }
-                                _ => {
-                                    if (dx > 0 && coordinate.x() > target.x())
-                                        || (dx < 0 && coordinate.x() < target.x())
-                                        || (dy > 0 && coordinate.y() < target.y())
-                                        || (dy < 0 && coordinate.y() > target.y())
-                                    {
-                                        break;
-                                    }
-                                }
-                            }
-                        }
-
-                        if has_line_of_sight {
-                            if let Some(coordinate) = potential_pin {
-                                result.set(coordinate, true);
-                            }
-                        }
-                    }
+            for end in attacks::pawn(color, start) & captures {
+                match self.board[end] {
+                    Some(_) => register_move(end, MoveFlag::Capture),
+                    None => register_move(end, MoveFlag::EnPassant),
                 }
             }
         }
-
-        Some(result)
     }
 
-    fn find_attackers(&self, target: Coordinate) -> Option<(Bitboard, Bitboard)> {
-        let piece = self.board[target]?;
-        let opponent = piece.0.opponent();
-
-        let mut attackers = Vec::with_capacity(2);
-
-        for y in 0..BOARD_HEIGHT {
-            for x in 0..BOARD_WIDTH {
-                let current = Coordinate::try_from(y * BOARD_WIDTH + x)
-                    .expect("The given index should always be within the board's length.");
-
-                match self.board[current] {
-                    Some(Piece(color, kind)) if color == opponent => {
-                        let move_list = (|| match kind {
-                            PieceKind::Pawn => {
-                                if current.x() == target.x() {
-                                    return None;
-                                }
-
-                                if (target.x() as i8 - current.x() as i8).abs() > 1 {
-                                    return None;
-                                }
-
-                                let dy: i8 = if color == Color::White { 1 } else { -1 };
-
-                                if let Ok(coordinate) = current.try_move(0, dy) {
-                                    if coordinate.y() != target.y() {
-                                        return None;
-                                    }
-                                }
-
-                                Some(self.generate_pseudo_legal_pawn_moves(current))
-                            }
-                            PieceKind::Knight => {
-                                Some(self.generate_pseudo_legal_knight_moves(current))
-                            }
-                            PieceKind::Bishop => {
-                                let difference_x = target.x() as i8 - current.x() as i8;
-                                let difference_y = target.y() as i8 - current.y() as i8;
-
-                                if difference_x.abs() != difference_y.abs() {
-                                    return None;
-                                }
-
-                                Some(self.generate_pseudo_legal_bishop_moves(current))
-                            }
-                            PieceKind::Rook => {
-                                if current.x() != target.x() && current.y() != target.y() {
-                                    return None;
-                                }
-
-                                Some(self.generate_pseudo_legal_rook_moves(current))
-                            }
-                            PieceKind::Queen => {
-                                let difference_x = target.x() as i8 - current.x() as i8;
-                                let difference_y = target.y() as i8 - current.y() as i8;
-
-                                if difference_x.abs() == difference_y.abs()
-                                    || current.x() == target.x()
-                                    || current.y() == target.y()
-                                {
-                                    return Some(self.generate_pseudo_legal_queen_moves(current));
-                                }
-
-                                None
-                            }
-                            PieceKind::King => Some(self.generate_pseudo_legal_king_moves(current)),
-                        })();
+    fn generate_pseudo_legal_knight_moves(
+        &self,
+        start: Coordinate,
+        targets: Bitboard,
+        moves: &mut MoveList,
+    ) {
+        if let Some(Piece(color, PieceKind::Knight)) = self.board[start] {
+            let targets = attacks::knight(start) & !self.board.occupancy_of(color) & targets;
 
-                        if let Some(move_list) = move_list {
-                            for lan in move_list {
-                                if lan.end == target {
-                                    attackers.push(lan.start);
-                                }
-                            }
-                        }
-                    }
-                    _ => (),
-                }
-            }
+            self.push_moves(moves, start, targets);
         }
+    }
 
-        let mut coordinates = Bitboard::empty();
-        let mut line_of_sight = Bitboard::empty();
-
-        for coordinate in attackers {
-            coordinates.set(coordinate, true);
-
-            if let Some(Piece(_, kind)) = self.board[coordinate] {
-                let direction = (|| match kind {
-                    PieceKind::Bishop => {
-                        let x = -(coordinate.x() as i8 - target.x() as i8).signum();
-                        let y = (coordinate.y() as i8 - target.y() as i8).signum();
-
-                        Some((x, y))
-                    }
-                    PieceKind::Rook => {
-                        let x = if coordinate.y() != target.y() {
-                            0
-                        } else {
-                            -(coordinate.x() as i8 - target.x() as i8).signum()
-                        };
-
-                        let y = if coordinate.x() != target.x() {
-                            0
-                        } else {
-                            (coordinate.y() as i8 - target.y() as i8).signum()
-                        };
-
-                        Some((x, y))
-                    }
-                    PieceKind::Queen => {
-                        if coordinate.x() != target.x() && coordinate.y() != target.y() {
-                            let x = -(coordinate.x() as i8 - target.x() as i8).signum();
-                            let y = (coordinate.y() as i8 - target.y() as i8).signum();
-
-                            return Some((x, y));
-                        }
-
-                        let x = if coordinate.y() != target.y() {
-                            0
-                        } else {
-                            -(coordinate.x() as i8 - target.x() as i8).signum()
-                        };
-
-                        let y = if coordinate.x() != target.x() {
-                            0
-                        } else {
-                            (coordinate.y() as i8 - target.y() as i8).signum()
-                        };
+    fn generate_pseudo_legal_bishop_moves(
+        &self,
+        start: Coordinate,
+        targets: Bitboard,
+        moves: &mut MoveList,
+    ) {
+        if let Some(Piece(color, PieceKind::Bishop)) = self.board[start] {
+            let targets = attacks::bishop(start, self.board.occupancy())
+                & !self.board.occupancy_of(color)
+                & targets;
 
-                        Some((x, y))
-                    }
-                    _ => None,
-                })();
+            self.push_moves(moves, start, targets);
+        }
+    }
 
-                if let Some(direction) = direction {
-                    let mut temp = coordinate.try_move(direction.0, direction.1);
+    fn generate_pseudo_legal_rook_moves(
+        &self,
+        start: Coordinate,
+        targets: Bitboard,
+        moves: &mut MoveList,
+    ) {
+        if let Some(Piece(color, PieceKind::Rook)) = self.board[start] {
+            let targets = attacks::rook(start, self.board.occupancy())
+                & !self.board.occupancy_of(color)
+                & targets;
 
-                    while let Ok(coordinate) = temp {
-                        temp = coordinate.try_move(direction.0, direction.1);
+            self.push_moves(moves, start, targets);
+        }
+    }
 
-                        if self.board[coordinate].is_none() {
-                            line_of_sight.set(coordinate, true);
-                        }
+    fn generate_pseudo_legal_queen_moves(
+        &self,
+        start: Coordinate,
+        targets: Bitboard,
+        moves: &mut MoveList,
+    ) {
+        if let Some(Piece(color, PieceKind::Queen)) = self.board[start] {
+            let targets = attacks::queen(start, self.board.occupancy())
+                & !self.board.occupancy_of(color)
+                & targets;
 
-                        if coordinate.x() == target.x() && coordinate.y() == target.y() {
-                            break;
-                        }
-                    }
-                }
-            }
+            self.push_moves(moves, start, targets);
         }
-
-        Some((coordinates, line_of_sight))
     }
 
-    fn sanitize_pinned_pawn(
+    fn generate_pseudo_legal_king_moves(
         &self,
-        move_list: &mut Vec<Lan>,
-        kings_coordinate: Coordinate,
-        coordinate: Coordinate,
+        start: Coordinate,
+        targets: Bitboard,
+        moves: &mut MoveList,
     ) {
-        match self.board[coordinate] {
-            Some(Piece(color, kind)) if kind == PieceKind::Pawn => {
-                // If a pinned pawn is diagonal to the king then its only move is capturing the attacker
-                // that is pinning it.
-                if coordinate.x() != kings_coordinate.x() && coordinate.y() != kings_coordinate.y()
-                {
-                    let ordering = match color {
-                        Color::White => Ordering::Greater,
-                        Color::Black => Ordering::Less,
-                    };
+        if let Some(Piece(color, PieceKind::King)) = self.board[start] {
+            let steps = attacks::king(start) & !self.board.occupancy_of(color) & targets;
 
-                    // If a white pawn is pinned and below the king or a black pawn is pinned and above the
-                    // king then it is not possible for it to capture the attacker that is pinning it.
-                    if ordering == coordinate.y().cmp(&kings_coordinate.y()) {
-                        move_list.clear();
+            self.push_moves(moves, start, steps);
 
-                        return;
-                    }
+            self.generate_castling_moves(start, targets, moves);
+        }
+    }
 
-                    let dx = (coordinate.x() as i8 - kings_coordinate.x() as i8).signum();
-                    let dy = match color {
-                        Color::White => 1,
-                        Color::Black => -1,
-                    };
-
-                    if let Ok(target) = coordinate.try_move(dx, dy) {
-                        match self.board[target] {
-                            Some(Piece(temp, _)) if temp == color.opponent() => {
-                                for i in (0..move_list.len()).rev() {
-                                    if move_list[i].end != target {
-                                        move_list.remove(i);
-                                    }
-                                }
-                            }
-                            _ => move_list.clear(),
-                        }
-                    }
+    /// Registers every castling move of the king on the given start whose passing and end
+    /// Coordinates are both within the given Bitboard.
+    fn generate_castling_moves(&self, start: Coordinate, safe: Bitboard, moves: &mut MoveList) {
+        let (color, castling_ability) = match (self.board[start], self.castling_ability) {
+            (Some(Piece(color, PieceKind::King)), Some(castling_ability)) => {
+                (color, castling_ability)
+            }
+            _ => return,
+        };
 
-                    return;
-                }
+        let king_side = match color {
+            Color::White => CastlingAbility::WHITE_KINGSIDE,
+            Color::Black => CastlingAbility::BLACK_KINGSIDE,
+        };
+        let queen_side = match color {
+            Color::White => CastlingAbility::WHITE_QUEENSIDE,
+            Color::Black => CastlingAbility::BLACK_QUEENSIDE,
+        };
 
-                // If a pinned pawn is in the same rank as the king then it cannot move.
-                if coordinate.y() == kings_coordinate.y() {
-                    move_list.clear();
+        let mut push_move = |passing: Coordinate, end: Coordinate, flag: MoveFlag| {
+            if safe.get(passing) && safe.get(end) {
+                moves.push(Move::new(start, end, flag));
+            }
+        };
 
-                    return;
+        if (castling_ability & king_side) != CastlingAbility::empty() {
+            if let (Ok(prerequisite), Ok(end)) = (start.try_move(1, 0), start.try_move(2, 0)) {
+                if let (None, None) = (self.board[prerequisite], self.board[end]) {
+                    push_move(prerequisite, end, MoveFlag::KingCastle);
                 }
+            }
+        }
 
-                // If a pinned pawn is in the same file as the king then it can only move along the file.
-                for i in (0..move_list.len()).rev() {
-                    if move_list[i].end.x() != coordinate.x() {
-                        move_list.remove(i);
-                    }
+        if (castling_ability & queen_side) != CastlingAbility::empty() {
+            if let (Ok(prerequisite_a), Ok(end), Ok(prerequisite_b)) = (
+                start.try_move(-1, 0),
+                start.try_move(-2, 0),
+                start.try_move(-3, 0),
+            ) {
+                if let (None, None, None) = (
+                    self.board[prerequisite_a],
+                    self.board[end],
+                    self.board[prerequisite_b],
+                ) {
+                    push_move(prerequisite_a, end, MoveFlag::QueenCastle);
                 }
             }
-            _ => panic!("Expected a pawn."),
         }
     }
 
-    fn sanitize_pinned_bishop(
+    fn generate_pseudo_legal_moves(
         &self,
-        move_list: &mut Vec<Lan>,
-        kings_coordinate: Coordinate,
-        coordinate: Coordinate,
+        start: Coordinate,
+        targets: Bitboard,
+        moves: &mut MoveList,
     ) {
-        match self.board[coordinate] {
-            Some(Piece(_, kind)) if kind == PieceKind::Bishop => {
-                // If a pinned bishop is on the same file or rank as the king then it cannot move.
-                if coordinate.x() == kings_coordinate.x() || coordinate.y() == kings_coordinate.y()
-                {
-                    move_list.clear();
-
-                    return;
-                }
-
-                let dx = (coordinate.x() as i8 - kings_coordinate.x() as i8).signum();
-                let dy = -(coordinate.y() as i8 - kings_coordinate.y() as i8).signum();
-
-                let mut bitboard = Bitboard::empty();
-                let mut temp = Ok(kings_coordinate);
-
-                while let Ok(target) = temp {
-                    temp = target.try_move(dx, dy);
-
-                    bitboard.set(target, true);
-                }
-
-                // Discard any moves that are outside of the king's diagonal.
-                for i in (0..move_list.len()).rev() {
-                    if !bitboard.get(move_list[i].end) {
-                        move_list.remove(i);
-                    }
-                }
+        match self.board[start] {
+            Some(Piece(_, PieceKind::Pawn)) => {
+                self.generate_pseudo_legal_pawn_moves(start, targets, moves)
             }
-            _ => panic!("Expected a bishop."),
+            Some(Piece(_, PieceKind::Knight)) => {
+                self.generate_pseudo_legal_knight_moves(start, targets, moves)
+            }
+            Some(Piece(_, PieceKind::Bishop)) => {
+                self.generate_pseudo_legal_bishop_moves(start, targets, moves)
+            }
+            Some(Piece(_, PieceKind::Rook)) => {
+                self.generate_pseudo_legal_rook_moves(start, targets, moves)
+            }
+            Some(Piece(_, PieceKind::Queen)) => {
+                self.generate_pseudo_legal_queen_moves(start, targets, moves)
+            }
+            Some(Piece(_, PieceKind::King)) => {
+                self.generate_pseudo_legal_king_moves(start, targets, moves)
+            }
+            None => (),
         }
     }
 
-    fn sanitize_pinned_rook(
-        &self,
-        move_list: &mut Vec<Lan>,
-        kings_coordinate: Coordinate,
-        coordinate: Coordinate,
-    ) {
-        match self.board[coordinate] {
-            Some(Piece(_, kind)) if kind == PieceKind::Rook => {
-                // If a pinned rook is not in the same file or rank as the king then it cannot
-                // move.
-                if coordinate.x() != kings_coordinate.x() && coordinate.y() != kings_coordinate.y()
-                {
-                    move_list.clear();
+    fn find_pins(&self, coordinate: Coordinate) -> Option<Bitboard> {
+        let target = coordinate;
+        let color = self.board[target]?.0;
+        let opponent = color.opponent();
 
-                    return;
-                }
+        let occupancy = self.board.occupancy();
+        let queens = self.board.find(opponent, PieceKind::Queen);
 
-                // Discard any moves that are not in the same file or rank as the king.
-                for i in (0..move_list.len()).rev() {
-                    let lan = move_list[i];
+        // Any attacker that would see the target on an empty board is a potential pinner.
+        let snipers = (attacks::rook(target, Bitboard::empty())
+            & (self.board.find(opponent, PieceKind::Rook) | queens))
+            | (attacks::bishop(target, Bitboard::empty())
+                & (self.board.find(opponent, PieceKind::Bishop) | queens));
 
-                    if (coordinate.x() == kings_coordinate.x()
-                        && lan.end.x() != kings_coordinate.x())
-                        || (coordinate.y() == kings_coordinate.y()
-                            && lan.end.y() != kings_coordinate.y())
-                    {
-                        move_list.remove(i);
-                    }
-                }
-            }
-            _ => panic!("Expected a rook."),
-        }
-    }
+        let mut result = Bitboard::empty();
 
-    fn sanitize_pinned_queen(
-        &self,
-        move_list: &mut Vec<Lan>,
-        kings_coordinate: Coordinate,
-        coordinate: Coordinate,
-    ) {
-        match self.board[coordinate] {
-            Some(Piece(_, kind)) if kind == PieceKind::Queen => {
-                // If a pinned queen is in the same file or rank as the king then discard any moves
-                // that are not in said file or rank.
-                if coordinate.x() == kings_coordinate.x() || coordinate.y() == kings_coordinate.y()
-                {
-                    for i in (0..move_list.len()).rev() {
-                        let lan = move_list[i];
+        for sniper in snipers {
+            let blockers = attacks::between(target, sniper) & occupancy;
 
-                        if (coordinate.x() == kings_coordinate.x()
-                            && lan.end.x() != kings_coordinate.x())
-                            || (coordinate.y() == kings_coordinate.y()
-                                && lan.end.y() != kings_coordinate.y())
-                        {
-                            move_list.remove(i);
-                        }
-                    }
+            // A piece is only pinned if it is the one and only piece standing in the way.
+            if blockers.population_count() == 1
+                && !(blockers & self.board.occupancy_of(color)).is_empty()
+            {
+                result |= blockers;
+            }
+        }
 
-                    return;
-                }
+        Some(result)
+    }
 
-                let dx = (coordinate.x() as i8 - kings_coordinate.x() as i8).signum();
-                let dy = -(coordinate.y() as i8 - kings_coordinate.y() as i8).signum();
+    fn find_attackers(&self, target: Coordinate) -> Option<(Bitboard, Bitboard)> {
+        let Piece(color, _) = self.board[target]?;
+        let opponent = color.opponent();
 
-                let mut bitboard = Bitboard::empty();
-                let mut temp = Ok(kings_coordinate);
+        let occupancy = self.board.occupancy();
+        let queens = self.board.find(opponent, PieceKind::Queen);
 
-                while let Ok(target) = temp {
-                    temp = target.try_move(dx, dy);
+        // Every piece attacks the target from the same squares the target would attack it from.
+        let coordinates = (attacks::pawn(color, target)
+            & self.board.find(opponent, PieceKind::Pawn))
+            | (attacks::knight(target) & self.board.find(opponent, PieceKind::Knight))
+            | (attacks::bishop(target, occupancy)
+                & (self.board.find(opponent, PieceKind::Bishop) | queens))
+            | (attacks::rook(target, occupancy)
+                & (self.board.find(opponent, PieceKind::Rook) | queens))
+            | (attacks::king(target) & self.board.find(opponent, PieceKind::King));
 
-                    bitboard.set(target, true);
-                }
+        let mut line_of_sight = Bitboard::empty();
 
-                // Discard any moves that are outside of the king's diagonal.
-                for i in (0..move_list.len()).rev() {
-                    if !bitboard.get(move_list[i].end) {
-                        move_list.remove(i);
-                    }
-                }
-            }
-            _ => panic!("Expected a queen."),
+        for coordinate in coordinates {
+            line_of_sight |= attacks::between(coordinate, target);
         }
+
+        Some((coordinates, line_of_sight))
     }
 
-    fn analyze(&self, color: Color) -> Analysis {
+    /// Generates every legal move of the given kind that the given Color can make.
+    ///
+    /// Checkmate and stalemate can only be told apart from check and safety when every move is
+    /// generated; otherwise the king is only ever considered to be in check or safe.
+    fn analyze(&self, color: Color, filter: MoveFilter) -> Analysis {
         let kings_coordinate = self
             .board
             .find_king(color)
@@ -2826,7 +3238,7 @@ impl State {
 
         let opponent = color.opponent();
 
-        let mut moves = self.generate_pseudo_legal_moves(color);
+        let mut moves = MoveList::new();
         let danger_zone = self.board.generate_danger_zone(opponent);
         let pins = self
             .find_pins(kings_coordinate)
@@ -2835,237 +3247,100 @@ impl State {
             .find_attackers(kings_coordinate)
             .expect("The given coordinates should always index a Some Piece.");
 
-        let mut can_move = false;
+        let check = danger_zone.get(kings_coordinate);
 
-        for y in 0..BOARD_HEIGHT {
-            for x in 0..BOARD_WIDTH {
-                let index = (y * BOARD_WIDTH + x) as usize;
-                let coordinate = Coordinate::try_from(index as u8)
-                    .expect("The given index should always be within the board's length.");
+        // If the king is under attack then the only valid move (besides moving the king) is either
+        // capturing the attacker or blocking the attacker's line of sight towards the king. The
+        // only response to a double check is moving the king.
+        let evasions = if !check {
+            !Bitboard::empty()
+        } else if attackers.0.population_count() >= 2 {
+            Bitboard::empty()
+        } else {
+            attackers.0 | attackers.1
+        };
 
-                match self.board[coordinate] {
-                    Some(Piece(temp, kind)) if temp == color => {
-                        let move_list = moves[index]
-                            .as_mut()
-                            .expect("A Some piece should always have a move list.");
-
-                        // Deal with pins.
-                        if pins.get(coordinate) {
-                            // If the king is under attack then a pinned piece cannot move.
-                            if danger_zone.get(kings_coordinate) {
-                                move_list.clear();
-
-                                continue;
-                            }
+        // Capturing en passant removes a pawn that is not on the Lan's end, which could be the
+        // attacker.
+        let en_passant_evasion = self.en_passant_target.filter(|&en_passant_target| {
+            let dy = match color {
+                Color::White => -1,
+                Color::Black => 1,
+            };
 
-                            match kind {
-                                PieceKind::Pawn => self.sanitize_pinned_pawn(
-                                    move_list,
-                                    kings_coordinate,
-                                    coordinate,
-                                ),
-                                PieceKind::Knight => move_list.clear(),
-                                PieceKind::Bishop => self.sanitize_pinned_bishop(
-                                    move_list,
-                                    kings_coordinate,
-                                    coordinate,
-                                ),
-                                PieceKind::Rook => self.sanitize_pinned_rook(
-                                    move_list,
-                                    kings_coordinate,
-                                    coordinate,
-                                ),
-                                PieceKind::Queen => self.sanitize_pinned_queen(
-                                    move_list,
-                                    kings_coordinate,
-                                    coordinate,
-                                ),
-                                PieceKind::King => {
-                                    panic!("It should not be possible for a king to be pinned.")
-                                }
-                            };
+            check
+                && en_passant_target
+                    .try_move(0, dy)
+                    .map(|coordinate| attackers.0.get(coordinate))
+                    .unwrap_or(false)
+        });
 
-                            continue;
-                        }
+        // Capturing en passant ends on an empty Coordinate, so pawns need their own targets.
+        let mut en_passant = Bitboard::empty();
 
-                        match kind {
-                            PieceKind::King => {
-                                const WHITE_KINGSIDE_LAN: Lan = Lan {
-                                    start: Coordinate::E1,
-                                    end: Coordinate::G1,
-                                    promotion: None,
-                                };
-                                const WHITE_QUEENSIDE_LAN: Lan = Lan {
-                                    start: Coordinate::E1,
-                                    end: Coordinate::C1,
-                                    promotion: None,
-                                };
-                                const BLACK_KINGSIDE_LAN: Lan = Lan {
-                                    start: Coordinate::E8,
-                                    end: Coordinate::G8,
-                                    promotion: None,
-                                };
-                                const BLACK_QUEENSIDE_LAN: Lan = Lan {
-                                    start: Coordinate::E8,
-                                    end: Coordinate::C8,
-                                    promotion: None,
-                                };
-
-                                for i in (0..move_list.len()).rev() {
-                                    let lan = move_list[i];
-
-                                    match lan {
-                                        Lan {
-                                            start,
-                                            end,
-                                            promotion: None,
-                                        } if (start == Coordinate::E1
-                                            && (end == Coordinate::G1
-                                                || end == Coordinate::C1))
-                                            || (start == Coordinate::E8
-                                                && (end == Coordinate::G8
-                                                    || end == Coordinate::C8)) =>
-                                        {
-                                            // If the king is under attack then it should not be
-                                            // able to castle.
-                                            if danger_zone.get(coordinate) {
-                                                move_list.remove(i);
-
-                                                continue;
-                                            }
-
-                                            // Make sure the king cannot castle through a check.
-                                            let king_side = match color {
-                                                Color::White => CastlingAbility::WHITE_KINGSIDE,
-                                                Color::Black => CastlingAbility::BLACK_KINGSIDE,
-                                            };
-                                            let queen_side = match color {
-                                                Color::White => CastlingAbility::WHITE_QUEENSIDE,
-                                                Color::Black => CastlingAbility::BLACK_QUEENSIDE,
-                                            };
-
-                                            let king_side_lan = match color {
-                                                Color::White => WHITE_KINGSIDE_LAN,
-                                                Color::Black => BLACK_KINGSIDE_LAN,
-                                            };
-                                            let queen_side_lan = match color {
-                                                Color::White => WHITE_QUEENSIDE_LAN,
-                                                Color::Black => BLACK_QUEENSIDE_LAN,
-                                            };
-
-                                            if let Some(castling_ability) = self.castling_ability {
-                                                if (castling_ability & king_side)
-                                                    != CastlingAbility::empty()
-                                                    && lan == king_side_lan
-                                                {
-                                                    if let (Ok(one), Ok(two)) = (
-                                                        coordinate.try_move(1, 0),
-                                                        coordinate.try_move(2, 0),
-                                                    ) {
-                                                        if danger_zone.get(one)
-                                                            || danger_zone.get(two)
-                                                        {
-                                                            move_list.remove(i);
-                                                        }
-                                                    }
-                                                }
-
-                                                if (castling_ability & queen_side)
-                                                    != CastlingAbility::empty()
-                                                    && lan == queen_side_lan
-                                                {
-                                                    if let (Ok(one), Ok(two)) = (
-                                                        coordinate.try_move(-1, 0),
-                                                        coordinate.try_move(-2, 0),
-                                                    ) {
-                                                        if danger_zone.get(one)
-                                                            || danger_zone.get(two)
-                                                        {
-                                                            move_list.remove(i);
-                                                        }
-                                                    }
-                                                }
-
-                                                continue;
-                                            }
-                                        }
-                                        _ => (),
-                                    }
-
-                                    // The king should not be able to walk into an attack.
-                                    if danger_zone.get(lan.end) {
-                                        move_list.remove(i);
-                                    }
-                                }
-                            }
-                            _ => {
-                                // Moves only need to be sanitized if the king is under attack.
-                                if !danger_zone.get(kings_coordinate) {
-                                    if !can_move && !move_list.is_empty() {
-                                        can_move = true;
-                                    }
+        if let Some(en_passant_target) = self.en_passant_target {
+            if color == self.side_to_move {
+                en_passant.set(en_passant_target, true);
+            }
+        }
 
-                                    continue;
-                                }
+        let captures = self.board.occupancy_of(opponent);
+        let quiets = !self.board.occupancy();
 
-                                // The only response to a double check is moving the king.
-                                if attackers.0.population_count() >= 2 {
-                                    move_list.clear();
+        let (targets, pawn_targets) = match filter {
+            MoveFilter::All => (!Bitboard::empty(), !Bitboard::empty()),
+            MoveFilter::Captures => (captures, captures | en_passant),
+            MoveFilter::Quiets => (quiets, quiets & !en_passant),
+        };
 
-                                    continue;
-                                }
+        for coordinate in self.board.occupancy_of(color) {
+            let Piece(_, kind) = self.board[coordinate]
+                .expect("An occupied Coordinate should always index a Some Piece.");
 
-                                for i in (0..move_list.len()).rev() {
-                                    let lan = move_list[i];
-
-                                    // If the king is under attack then the only valid move is
-                                    // either capturing the attacker or blocking the attacker's
-                                    // line of sight towards the king.
-                                    if attackers.0.get(lan.end) || attackers.1.get(lan.end) {
-                                        continue;
-                                    }
-
-                                    // Check if capturing en passant captures an attacker.
-                                    if let Some(en_passant_target) = self.en_passant_target {
-                                        if kind == PieceKind::Pawn && lan.end == en_passant_target {
-                                            let dy = match color {
-                                                Color::White => -1,
-                                                Color::Black => 1,
-                                            };
-
-                                            if let Ok(coordinate) =
-                                                en_passant_target.try_move(0, dy)
-                                            {
-                                                if attackers.0.get(coordinate) {
-                                                    match self.board[coordinate] {
-                                                        Some(Piece(temp, PieceKind::Pawn))
-                                                            if temp == opponent =>
-                                                        {
-                                                            continue;
-                                                        }
-                                                        _ => (),
-                                                    }
-                                                }
-                                            }
-                                        }
-                                    }
-
-                                    move_list.remove(i);
-                                }
-                            }
-                        }
+            if kind == PieceKind::King {
+                // The king should not be able to walk into an attack.
+                let steps = attacks::king(coordinate)
+                    & !self.board.occupancy_of(color)
+                    & !danger_zone
+                    & targets;
 
-                        if !can_move && !move_list.is_empty() {
-                            can_move = true;
-                        }
-                    }
-                    _ => (),
+                self.push_moves(&mut moves, coordinate, steps);
+
+                // Make sure the king cannot castle out of or through a check.
+                if !check && filter != MoveFilter::Captures {
+                    self.generate_castling_moves(coordinate, !danger_zone, &mut moves);
                 }
+
+                continue;
             }
+
+            let pinned = pins.get(coordinate);
+
+            let mut allowed = evasions;
+
+            // A pinned piece can only move along the line between its king and the piece pinning
+            // it.
+            if pinned {
+                allowed &= attacks::line(kings_coordinate, coordinate);
+            }
+
+            if kind == PieceKind::Pawn {
+                if let (Some(en_passant_evasion), false) = (en_passant_evasion, pinned) {
+                    allowed.set(en_passant_evasion, true);
+                }
+
+                allowed &= pawn_targets;
+            } else {
+                allowed &= targets;
+            }
+
+            self.generate_pseudo_legal_moves(coordinate, allowed, &mut moves);
         }
 
+        let can_move = !moves.is_empty() || filter != MoveFilter::All;
+
         let king_safety = {
-            if danger_zone.get(kings_coordinate) {
+            if check {
                 if can_move {
                     KingSafety::Check
                 } else {
@@ -3081,7 +3356,6 @@ impl State {
         Analysis {
             moves,
             danger_zone,
-            king_location: kings_coordinate,
             king_safety,
         }
     }
@@ -3091,125 +3365,322 @@ impl From<Fen> for State {
     fn from(value: Fen) -> Self {
         let board = Board::from(&value.placement);
 
-        State {
+        let mut state = State {
             board,
             side_to_move: value.side_to_move,
             castling_ability: value.castling_ability,
             en_passant_target: value.en_passant_target,
             half_moves: value.half_moves,
             full_moves: value.full_moves,
+            hash: 0,
+        };
+
+        state.hash = state.zobrist();
+
+        state
+    }
+}
+
+impl From<State> for Fen {
+    fn from(value: State) -> Self {
+        let placement = Placement::from(value.board);
+
+        Fen {
+            placement,
+            side_to_move: value.side_to_move,
+            castling_ability: value.castling_ability,
+            en_passant_target: value.en_passant_target,
+            half_moves: value.half_moves,
+            full_moves: value.full_moves,
+        }
+    }
+}
+
+impl From<State> for String {
+    fn from(value: State) -> Self {
+        String::from(&Fen::from(value))
+    }
+}
+
+impl Display for State {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", String::from(*self))
+    }
+}
+
+/// Everything a search shares between nodes; the bounds of a node are passed separately.
+struct SearchParams<'a> {
+    state: &'a mut State,
+    searched: u128,
+    /// The hashes of every State leading up to the current one.
+    history: &'a mut Vec<u64>,
+    /// The length of `history` when the search started.
+    root: usize,
+    limits: &'a SearchLimits<'a>,
+    table: &'a mut TranspositionTable,
+    pv: PrincipalVariation,
+    quiet_history: &'a mut QuietHistory,
+    /// The move that was made at each ply of the current branch, where None is a null move.
+    played: [Option<Move>; MAX_PLY],
+    /// The depth that the search started with at the root.
+    depth: u8,
+    /// Whether or not quiet moves that were ordered late may be reduced or pruned.
+    prune_late_moves: bool,
+}
+
+/// The best line found from every ply of the current branch, stored as a triangular table.
+///
+/// Learn more about the principal variation here: https://www.chessprogramming.org/Triangular_PV-Table
+struct PrincipalVariation {
+    moves: [[Move; MAX_PLY]; MAX_PLY],
+    /// The ply at which the line that starts at each ply ends.
+    lengths: [usize; MAX_PLY],
+}
+
+impl PrincipalVariation {
+    fn new() -> Self {
+        PrincipalVariation {
+            moves: [[Move(0); MAX_PLY]; MAX_PLY],
+            lengths: [0; MAX_PLY],
+        }
+    }
+
+    /// Forgets the line that starts at the given ply.
+    fn clear(&mut self, ply: usize) {
+        self.lengths[ply] = ply;
+    }
+
+    /// Replaces the line that starts at the given ply with the given move followed by the line of
+    /// the next ply.
+    fn update(&mut self, ply: usize, mv: Move) {
+        self.moves[ply][ply] = mv;
+
+        for i in (ply + 1)..self.lengths[ply + 1] {
+            self.moves[ply][i] = self.moves[ply + 1][i];
+        }
+
+        self.lengths[ply] = self.lengths[ply + 1];
+    }
+
+    /// The line that starts at the root of the search.
+    fn line(&self) -> &[Move] {
+        &self.moves[0][..self.lengths[0]]
+    }
+}
+
+/// What previous searches learned about quiet moves that caused cutoffs, which is used to search
+/// the most promising quiet moves first.
+///
+/// Learn more about these heuristics here: https://www.chessprogramming.org/Move_Ordering
+struct QuietHistory {
+    /// The last two quiet moves that caused a cutoff at each ply.
+    killers: [[Option<Move>; 2]; MAX_PLY],
+    /// How often each quiet move caused a cutoff, indexed by color, start, and then end.
+    butterfly: [[[i16; 64]; 64]; 2],
+    /// The quiet move that last refuted each move, indexed by the refuted move's start and end.
+    countermoves: [[Option<Move>; 64]; 64],
+}
+
+impl Default for QuietHistory {
+    fn default() -> Self {
+        QuietHistory {
+            killers: [[None; 2]; MAX_PLY],
+            butterfly: [[[0; 64]; 64]; 2],
+            countermoves: [[None; 64]; 64],
+        }
+    }
+}
+
+impl QuietHistory {
+    /// Scores how promising a quiet move looks before it is searched.
+    fn score(&self, color: Color, mv: Move, ply: usize, previous: Option<Move>) -> i32 {
+        let killers = self.killers[ply];
+
+        if killers[0] == Some(mv) {
+            return KILLER_ORDER;
+        }
+
+        if killers[1] == Some(mv) {
+            return KILLER_ORDER - 1;
         }
+
+        if let Some(previous) = previous {
+            if self.countermoves[previous.start() as usize][previous.end() as usize] == Some(mv) {
+                return COUNTERMOVE_ORDER;
+            }
+        }
+
+        self.butterfly[color as usize][mv.start() as usize][mv.end() as usize] as i32
     }
-}
 
-impl From<State> for Fen {
-    fn from(value: State) -> Self {
-        let placement = Placement::from(value.board);
+    /// Rewards the quiet move that caused a cutoff, and penalizes every quiet move that was
+    /// searched before it without causing one.
+    fn update(
+        &mut self,
+        color: Color,
+        mv: Move,
+        ply: usize,
+        previous: Option<Move>,
+        depth: u8,
+        failed: &[Move],
+    ) {
+        if self.killers[ply][0] != Some(mv) {
+            self.killers[ply][1] = self.killers[ply][0];
+            self.killers[ply][0] = Some(mv);
+        }
 
-        Fen {
-            placement,
-            side_to_move: value.side_to_move,
-            castling_ability: value.castling_ability,
-            en_passant_target: value.en_passant_target,
-            half_moves: value.half_moves,
-            full_moves: value.full_moves,
+        if let Some(previous) = previous {
+            self.countermoves[previous.start() as usize][previous.end() as usize] = Some(mv);
+        }
+
+        let bonus = (depth as i32 * depth as i32).min(MAX_HISTORY);
+
+        self.adjust(color, mv, bonus);
+
+        for &failed in failed {
+            self.adjust(color, failed, -bonus);
         }
     }
-}
 
-impl From<State> for String {
-    fn from(value: State) -> Self {
-        String::from(&Fen::from(value))
+    /// Moves the history of the given move towards the given bonus; the closer the history already
+    /// is to `MAX_HISTORY`, the less it changes.
+    fn adjust(&mut self, color: Color, mv: Move, bonus: i32) {
+        let entry = &mut self.butterfly[color as usize][mv.start() as usize][mv.end() as usize];
+        let value = *entry as i32;
+
+        *entry = (value + bonus - value * bonus.abs() / MAX_HISTORY) as i16;
     }
-}
 
-impl Display for State {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "{}", String::from(*self))
+    fn clear(&mut self) {
+        *self = QuietHistory::default();
     }
 }
 
-#[derive(Clone, Copy)]
-enum Strategy {
-    Maximizing,
-    Minimizing,
+/// Conditions that cut a search short.
+#[derive(Default)]
+struct SearchLimits<'a> {
+    stop: Option<&'a AtomicBool>,
+    /// The timestamp (in milliseconds) at which the search must end.
+    deadline: Option<u64>,
+    /// The amount of nodes the search is allowed to visit.
+    nodes: Option<u128>,
+    aborted: Cell<bool>,
 }
 
-impl Strategy {
-    fn opposite(&self) -> Strategy {
-        match &self {
-            Strategy::Maximizing => Strategy::Minimizing,
-            Strategy::Minimizing => Strategy::Maximizing,
+impl<'a> SearchLimits<'a> {
+    fn should_abort(&self, searched: u128) -> bool {
+        if self.aborted.get() {
+            return true;
         }
-    }
-}
 
-impl From<Color> for Strategy {
-    fn from(value: Color) -> Self {
-        match value {
-            Color::White => Strategy::Maximizing,
-            Color::Black => Strategy::Minimizing,
+        if let Some(nodes) = self.nodes {
+            if searched >= nodes {
+                self.aborted.set(true);
+            }
+        }
+
+        if let Some(stop) = self.stop {
+            if stop.load(atomic::Ordering::Relaxed) {
+                self.aborted.set(true);
+            }
         }
+
+        if let Some(deadline) = self.deadline {
+            if utils::timestamp() >= deadline {
+                self.aborted.set(true);
+            }
+        }
+
+        self.aborted.get()
     }
 }
 
+/// How a score stored in the TranspositionTable relates to the actual value of a State.
 #[derive(Debug, PartialEq, Eq, Clone, Copy)]
-enum Evaluation {
-    Winner(Color),
-    Draw,
-    Static(i16),
+enum Bound {
+    Exact,
+    /// The actual value is at least the stored score.
+    Lower,
+    /// The actual value is at most the stored score.
+    Upper,
 }
 
-impl Evaluation {
-    fn min(&self, value: Evaluation) -> Evaluation {
-        let left = i16::from(*self);
-        let right = i16::from(value);
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+struct TableEntry {
+    hash: u64,
+    depth: u8,
+    bound: Bound,
+    /// The score relative to the side to move.
+    score: i16,
+    best: Option<Move>,
+    /// The search that stored the entry.
+    generation: u8,
+}
 
-        match left.cmp(&right) {
-            Ordering::Less | Ordering::Equal => *self,
-            Ordering::Greater => value,
-        }
+/// A fixed-size cache of previously searched States.
+///
+/// Learn more about transposition tables here: https://www.chessprogramming.org/Transposition_Table
+struct TranspositionTable {
+    entries: Vec<Option<TableEntry>>,
+    generation: u8,
+}
+
+impl Default for TranspositionTable {
+    fn default() -> Self {
+        TranspositionTable::new(DEFAULT_HASH_SIZE)
     }
+}
 
-    fn max(&self, value: Evaluation) -> Evaluation {
-        let left = i16::from(*self);
-        let right = i16::from(value);
+impl TranspositionTable {
+    fn new(megabytes: usize) -> Self {
+        let megabytes = megabytes.clamp(MIN_HASH_SIZE, MAX_HASH_SIZE);
+        let length = megabytes * 1024 * 1024 / std::mem::size_of::<Option<TableEntry>>();
 
-        match left.cmp(&right) {
-            Ordering::Greater | Ordering::Equal => *self,
-            Ordering::Less => value,
+        TranspositionTable {
+            entries: vec![None; length],
+            generation: 0,
         }
     }
-}
 
-impl From<Evaluation> for i16 {
-    fn from(value: Evaluation) -> Self {
-        match value {
-            Evaluation::Winner(side) => match side {
-                Color::White => CHECKMATE_EVALUATION,
-                Color::Black => -CHECKMATE_EVALUATION,
-            },
-            Evaluation::Draw => 0,
-            Evaluation::Static(value) => value,
+    fn index(&self, hash: u64) -> usize {
+        (hash % self.entries.len() as u64) as usize
+    }
+
+    fn probe(&self, hash: u64) -> Option<TableEntry> {
+        self.entries[self.index(hash)].filter(|entry| entry.hash == hash)
+    }
+
+    fn store(&mut self, entry: TableEntry) {
+        let index = self.index(entry.hash);
+        let entry = TableEntry {
+            generation: self.generation,
+            ..entry
+        };
+
+        // Prefer keeping entries from the current search that were searched deeper.
+        let replace = match self.entries[index] {
+            Some(existing) => {
+                existing.hash == entry.hash
+                    || existing.generation != self.generation
+                    || existing.depth <= entry.depth
+            }
+            None => true,
+        };
+
+        if replace {
+            self.entries[index] = Some(entry);
         }
     }
-}
 
-struct MinimaxParams<'a> {
-    state: &'a mut State,
-    depth: u8,
-    searched: &'a mut u128,
-    line: &'a Option<Vec<Lan>>,
-    alpha: i16,
-    beta: i16,
-    strategy: Strategy,
-}
+    /// Marks every entry that is currently stored as stale.
+    fn age(&mut self) {
+        self.generation = self.generation.wrapping_add(1);
+    }
 
-struct SearchNode {
-    evaluation: Evaluation,
-    /// The move that resulted in this state.
-    transformation: Option<Lan>,
-    child: Option<Box<SearchNode>>,
+    fn clear(&mut self) {
+        self.entries.iter_mut().for_each(|entry| *entry = None);
+        self.generation = 0;
+    }
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -3341,19 +3812,110 @@ impl Display for Suggestion {
     }
 }
 
+/// The state of both players' clocks (in milliseconds) as reported by `go`.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+struct Clock {
+    wtime: Option<u64>,
+    btime: Option<u64>,
+    winc: u64,
+    binc: u64,
+    movestogo: Option<u64>,
+}
+
+/// How long (in milliseconds) the engine is allowed to think about its next move.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct TimeBudget {
+    /// Once this much time has passed, the engine should not start another iteration.
+    soft: u64,
+    /// Once this much time has passed, the engine must stop searching immediately.
+    hard: u64,
+}
+
+impl TimeBudget {
+    /// Time reserved for the communication between the engine and its host.
+    const MOVE_OVERHEAD: u64 = 50;
+    /// The amount of moves the engine expects to still play when the time control does not say.
+    const EXPECTED_MOVES: u64 = 30;
+    /// The least amount of time the engine thinks about a move, even if the clock says otherwise.
+    const MINIMUM: u64 = 10;
+
+    fn new(clock: Clock, side: Color) -> Self {
+        let (time, increment) = match side {
+            Color::White => (clock.wtime, clock.winc),
+            Color::Black => (clock.btime, clock.binc),
+        };
+
+        let available = time.unwrap_or(0).saturating_sub(TimeBudget::MOVE_OVERHEAD);
+        let moves = clock
+            .movestogo
+            .unwrap_or(TimeBudget::EXPECTED_MOVES)
+            .clamp(1, TimeBudget::EXPECTED_MOVES);
+
+        // Never plan on spending more than half of the remaining time, even if this is the last
+        // move before the next time control.
+        let soft = (available / moves + increment * 3 / 4).min(available / 2);
+        let hard = (soft * 3).min(available * 3 / 4);
+
+        TimeBudget {
+            soft: soft.max(TimeBudget::MINIMUM),
+            hard: hard.max(TimeBudget::MINIMUM),
+        }
+    }
+
+    fn fixed(movetime: u64) -> Self {
+        let time = movetime
+            .saturating_sub(TimeBudget::MOVE_OVERHEAD)
+            .max(TimeBudget::MINIMUM);
+
+        TimeBudget {
+            soft: time,
+            hard: time,
+        }
+    }
+}
+
+/// Restrictions placed on iterative deepening besides its maximum depth.
+#[derive(Debug, Default, Clone, Copy)]
+struct GoLimits {
+    budget: Option<TimeBudget>,
+    nodes: Option<u128>,
+    /// Whether or not the engine should wait for `stop` before suggesting a move.
+    infinite: bool,
+}
+
+/// Every limit a `go` command placed on the search; whichever is reached first ends it.
+#[derive(Debug, Default, Clone, Copy)]
+struct GoSearch {
+    depth: Option<u8>,
+    clock: Option<Clock>,
+    movetime: Option<u64>,
+    nodes: Option<u128>,
+    infinite: bool,
+}
+
 // TODO(thismarvin): This definitely needs a better name... right?
 #[derive(Debug, Clone, Copy)]
 enum GoParams {
-    Depth(u8),
+    Search(GoSearch),
     Perft(u8),
 }
 
 #[derive(Debug, Clone, Copy)]
+enum EngineOption {
+    /// The size (in megabytes) of the transposition table.
+    Hash(usize),
+}
+
+#[derive(Debug, Clone)]
 enum Command {
     Uci,
     Isready,
-    Position(State),
+    Setoption(EngineOption),
+    Ucinewgame,
+    /// The State the game started from, and every move that was played since.
+    Position(State, Vec<Lan>),
     Go(GoParams),
+    Stop,
     Quit,
     // The following are non-standard commands.
     D,
@@ -3372,6 +3934,37 @@ impl TryFrom<&str> for Command {
             return Ok(Command::Isready);
         }
 
+        if value.starts_with("setoption") {
+            let sections = value.split_whitespace().collect::<Vec<&str>>();
+
+            return match sections[1..] {
+                ["name", "Hash", "value", size] => {
+                    let size = size.parse::<usize>().map_err(|_| {
+                        ChessError(
+                            ChessErrorKind::InvalidString,
+                            "Expected a valid number to follow \"setoption name Hash value\".",
+                        )
+                    })?;
+
+                    Ok(Command::Setoption(EngineOption::Hash(
+                        size.clamp(MIN_HASH_SIZE, MAX_HASH_SIZE),
+                    )))
+                }
+                ["name", ..] => Err(ChessError(
+                    ChessErrorKind::InvalidString,
+                    "Unknown option; expected <Hash>.",
+                )),
+                _ => Err(ChessError(
+                    ChessErrorKind::InvalidString,
+                    "Expected \"setoption name <id> value <x>\".",
+                )),
+            };
+        }
+
+        if value == "ucinewgame" {
+            return Ok(Command::Ucinewgame);
+        }
+
         if value.starts_with("position") {
             let mut sections = value.split_whitespace().skip(1);
 
@@ -3382,7 +3975,8 @@ impl TryFrom<&str> for Command {
 
             return match next {
                 "startpos" => {
-                    let mut state = State::default();
+                    let state = State::default();
+                    let mut moves = Vec::new();
 
                     if let Some(subcommand) = sections.next() {
                         match subcommand {
@@ -3400,7 +3994,12 @@ impl TryFrom<&str> for Command {
                                     sequence.push(lan);
                                 }
 
-                                Engine::make_sequence(&mut state, &sequence)?;
+                                // Make sure every move is legal before accepting the position.
+                                let mut copy = state;
+
+                                Engine::make_sequence(&mut copy, &sequence)?;
+
+                                moves = sequence;
                             }
                             _ => {
                                 return Err(ChessError(
@@ -3411,7 +4010,7 @@ impl TryFrom<&str> for Command {
                         }
                     }
 
-                    Ok(Command::Position(state))
+                    Ok(Command::Position(state, moves))
                 }
                 "fen" => {
                     let placement = sections.next().ok_or(ChessError(
@@ -3461,7 +4060,8 @@ impl TryFrom<&str> for Command {
                         )
                     })?;
 
-                    let mut state = State::from(fen);
+                    let state = State::from(fen);
+                    let mut moves = Vec::new();
 
                     if let Some(subcommand) = sections.next() {
                         match subcommand {
@@ -3479,7 +4079,12 @@ impl TryFrom<&str> for Command {
                                     sequence.push(lan);
                                 }
 
-                                Engine::make_sequence(&mut state, &sequence)?;
+                                // Make sure every move is legal before accepting the position.
+                                let mut copy = state;
+
+                                Engine::make_sequence(&mut copy, &sequence)?;
+
+                                moves = sequence;
                             }
                             _ => {
                                 return Err(ChessError(
@@ -3490,7 +4095,7 @@ impl TryFrom<&str> for Command {
                         }
                     }
 
-                    Ok(Command::Position(state))
+                    Ok(Command::Position(state, moves))
                 }
                 _ => Err(ChessError(
                     ChessErrorKind::InvalidString,
@@ -3502,47 +4107,97 @@ impl TryFrom<&str> for Command {
         if value.starts_with("go") {
             let mut sections = value.split_whitespace().skip(1);
 
-            let next = sections.next().ok_or(ChessError(
-                ChessErrorKind::InvalidString,
-                "Expected <depth | perft> subcommand.",
-            ))?;
-
-            return match next {
-                "depth" => {
-                    let depth = sections.next().ok_or(ChessError(
-                        ChessErrorKind::InvalidString,
-                        "Expected a valid u8 string to follow \"go depth\".",
-                    ))?;
+            let mut search = GoSearch::default();
 
-                    let depth = depth.parse::<u8>().map_err(|_| {
-                        ChessError(
+            while let Some(next) = sections.next() {
+                match next {
+                    "perft" => {
+                        let depth = sections.next().ok_or(ChessError(
                             ChessErrorKind::InvalidString,
-                            "The given string is not a valid u8 string.",
-                        )
-                    })?;
+                            "Expected a valid u8 string to follow \"go perft\".",
+                        ))?;
 
-                    Ok(Command::Go(GoParams::Depth(depth)))
-                }
-                "perft" => {
-                    let depth = sections.next().ok_or(ChessError(
-                        ChessErrorKind::InvalidString,
-                        "Expected a valid u8 string to follow \"go perft\".",
-                    ))?;
+                        let depth = depth.parse::<u8>().map_err(|_| {
+                            ChessError(
+                                ChessErrorKind::InvalidString,
+                                "The given string is not a valid u8 string.",
+                            )
+                        })?;
 
-                    let depth = depth.parse::<u8>().map_err(|_| {
-                        ChessError(
+                        return Ok(Command::Go(GoParams::Perft(depth)));
+                    }
+                    "depth" => {
+                        let value = sections.next().ok_or(ChessError(
                             ChessErrorKind::InvalidString,
-                            "The given string is not a valid u8 string.",
-                        )
-                    })?;
+                            "Expected a valid u8 string to follow \"go depth\".",
+                        ))?;
 
-                    Ok(Command::Go(GoParams::Perft(depth)))
+                        let value = value.parse::<u8>().map_err(|_| {
+                            ChessError(
+                                ChessErrorKind::InvalidString,
+                                "The given string is not a valid u8 string.",
+                            )
+                        })?;
+
+                        search.depth = Some(value);
+                    }
+                    "movetime" => {
+                        let value = sections
+                            .next()
+                            .and_then(|value| value.parse::<u64>().ok())
+                            .ok_or(ChessError(
+                                ChessErrorKind::InvalidString,
+                                "Expected a valid number to follow \"go movetime\".",
+                            ))?;
+
+                        search.movetime = Some(value);
+                    }
+                    "nodes" => {
+                        let value = sections
+                            .next()
+                            .and_then(|value| value.parse::<u128>().ok())
+                            .ok_or(ChessError(
+                                ChessErrorKind::InvalidString,
+                                "Expected a valid number to follow \"go nodes\".",
+                            ))?;
+
+                        search.nodes = Some(value);
+                    }
+                    "infinite" => {
+                        search.infinite = true;
+                    }
+                    "wtime" | "btime" | "winc" | "binc" | "movestogo" => {
+                        // Some hosts report negative time once a player's flag has fallen.
+                        let value = sections
+                            .next()
+                            .and_then(|value| value.parse::<i64>().ok())
+                            .ok_or(ChessError(
+                                ChessErrorKind::InvalidString,
+                                "Expected a valid number to follow the given subcommand.",
+                            ))?
+                            .max(0) as u64;
+
+                        let clock = search.clock.get_or_insert_with(Clock::default);
+
+                        match next {
+                            "wtime" => clock.wtime = Some(value),
+                            "btime" => clock.btime = Some(value),
+                            "winc" => clock.winc = value,
+                            "binc" => clock.binc = value,
+                            _ => clock.movestogo = Some(value),
+                        }
+                    }
+                    // Anything else (e.g. `ponder`, `searchmoves`, or `mate`) is not supported, and
+                    // is skipped so that the limits that are supported still apply.
+                    _ => (),
                 }
-                _ => Err(ChessError(
-                    ChessErrorKind::InvalidString,
-                    "The given subcommand is not valid; expected <depth | perft>",
-                )),
-            };
+            }
+
+            return Ok(Command::Go(GoParams::Search(search)));
+        }
+
+        if value == "stop" {
+            return Ok(Command::Stop);
         }
 
         if value == "quit" {
@@ -3564,30 +4219,74 @@ impl TryFrom<&str> for Command {
     }
 }
 
+/// How many plies a late move is reduced by, indexed by depth and then by the move's index.
+type ReductionTable = [[u8; MAX_MOVES]; MAX_SEARCH_DEPTH as usize + 1];
+
+static LATE_MOVE_REDUCTIONS: OnceLock<ReductionTable> = OnceLock::new();
+
+fn late_move_reductions() -> &'static ReductionTable {
+    LATE_MOVE_REDUCTIONS.get_or_init(|| {
+        let mut table = [[0; MAX_MOVES]; MAX_SEARCH_DEPTH as usize + 1];
+
+        for (depth, reductions) in table.iter_mut().enumerate().skip(1) {
+            for (index, reduction) in reductions.iter_mut().enumerate().skip(1) {
+                let value = LMR_BASE + (depth as f32).ln() * (index as f32).ln() / LMR_DIVISOR;
+
+                *reduction = value as u8;
+            }
+        }
+
+        table
+    })
+}
+
 pub struct Engine;
 
 impl Engine {
-    fn make_sequence(state: &mut State, sequence: &[Lan]) -> Result<(), ChessError> {
-        for lan in sequence {
-            let analysis = state.analyze(state.side_to_move);
+    /// Makes every move in the given sequence, and returns the hash of each State that was left
+    /// behind along the way.
+    fn make_sequence(state: &mut State, sequence: &[Lan]) -> Result<Vec<u64>, ChessError> {
+        let mut history = Vec::with_capacity(sequence.len());
 
-            if let Some(list) = &analysis.moves[lan.start as usize] {
-                if list.contains(lan) {
-                    state
-                        .make_move(*lan)
-                        .expect("The given move should always be valid.");
+        for &lan in sequence {
+            let hash = state.hash;
 
-                    continue;
-                }
+            state.play(lan).map_err(|_| {
+                ChessError(
+                    ChessErrorKind::Other,
+                    "A move in the given sequence is not legal.",
+                )
+            })?;
+
+            history.push(hash);
+        }
+
+        Ok(history)
+    }
+
+    /// Determines whether or not the given State should be treated as a draw by repetition.
+    ///
+    /// A State that repeats any State within the search is scored as a draw right away; there is
+    /// no point in searching the same position twice. However, States that only occurred before
+    /// the search started need to have occurred twice already (i.e. threefold repetition).
+    fn is_repetition(state: &State, history: &[u64], root: usize) -> bool {
+        // Captures and pawn moves are irreversible, so there is no need to look any further back.
+        let start = history.len().saturating_sub(state.half_moves);
+        let mut occurrences = 0;
+
+        for (i, &hash) in history.iter().enumerate().skip(start) {
+            if hash != state.hash {
+                continue;
             }
 
-            return Err(ChessError(
-                ChessErrorKind::Other,
-                "A move in the given sequence is not legal.",
-            ));
+            if i >= root {
+                return true;
+            }
+
+            occurrences += 1;
         }
 
-        Ok(())
+        occurrences >= 2
     }
 
     pub fn perft(state: &mut State, depth: u8) -> u128 {
@@ -3595,59 +4294,130 @@ impl Engine {
             return 1;
         }
 
-        let analysis = state.analyze(state.side_to_move);
+        let analysis = state.analyze(state.side_to_move, MoveFilter::All);
 
         // At a depth of one, the total amount of legal moves is the perft value.
         if depth == 1 {
-            return analysis
-                .moves
-                .iter()
-                .filter_map(|entry| entry.as_ref())
-                .fold(0, |accumulator, entry| accumulator + entry.len() as u128);
+            return analysis.moves.len() as u128;
         }
 
         let mut total = 0;
 
-        for move_list in analysis.moves.into_iter().flatten() {
-            for lan in move_list {
+        for &lan in analysis.moves.iter() {
+            let undoer = state
+                .make_move(lan)
+                .expect("The given move should always be valid");
+
+            total += Engine::perft(state, depth - 1);
+
+            state.unmake_move(undoer);
+        }
+
+        total
+    }
+
+    /// Counts the same leaves as `perft`, but splits the work between the given amount of threads.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn perft_parallel(state: &State, depth: u8, threads: usize) -> u128 {
+        let mut state = *state;
+
+        // There is not enough work to be worth splitting up.
+        if depth <= 2 || threads <= 1 {
+            return Engine::perft(&mut state, depth);
+        }
+
+        // Splitting two plies deep (rather than at the root) gives the threads hundreds of smaller
+        // subtrees to share, so that none of them sit idle while one finishes a large subtree.
+        let mut subtrees = Vec::new();
+
+        for &mv in state
+            .analyze(state.side_to_move, MoveFilter::All)
+            .moves
+            .iter()
+        {
+            let undoer = state
+                .make_move(mv)
+                .expect("The given move should always be valid");
+
+            for &reply in state
+                .analyze(state.side_to_move, MoveFilter::All)
+                .moves
+                .iter()
+            {
                 let undoer = state
-                    .make_move(lan)
+                    .make_move(reply)
                     .expect("The given move should always be valid");
 
-                total += Engine::perft(state, depth - 1);
+                subtrees.push(state);
 
                 state.unmake_move(undoer);
             }
+
+            state.unmake_move(undoer);
         }
 
-        total
+        let next = atomic::AtomicUsize::new(0);
+
+        std::thread::scope(|scope| {
+            let workers = (0..threads.min(subtrees.len()))
+                .map(|_| {
+                    scope.spawn(|| {
+                        let mut total = 0;
+
+                        while let Some(subtree) =
+                            subtrees.get(next.fetch_add(1, atomic::Ordering::Relaxed))
+                        {
+                            total += Engine::perft(&mut subtree.clone(), depth - 2);
+                        }
+
+                        total
+                    })
+                })
+                .collect::<Vec<_>>();
+
+            workers
+                .into_iter()
+                .map(|worker| worker.join().expect("A perft thread should never panic"))
+                .sum()
+        })
     }
 
-    fn evaluate(state: State) -> Evaluation {
-        let white_analysis = state.analyze(Color::White);
-        let black_analysis = state.analyze(Color::Black);
+    /// Scores the given State relative to the side to move.
+    fn evaluate(state: State) -> i16 {
+        let white_analysis = state.analyze(Color::White, MoveFilter::All);
+        let black_analysis = state.analyze(Color::Black, MoveFilter::All);
+
+        let relative = |score: i16| match state.side_to_move {
+            Color::White => score,
+            Color::Black => -score,
+        };
 
         if white_analysis.king_safety == KingSafety::Checkmate {
-            return Evaluation::Winner(Color::Black);
+            return relative(-CHECKMATE_EVALUATION);
         }
 
         if black_analysis.king_safety == KingSafety::Checkmate {
-            return Evaluation::Winner(Color::White);
+            return relative(CHECKMATE_EVALUATION);
         }
 
         // Draws
-        // TODO(thismarvin): How will this function handle other types of draws?
+        // Note that repetitions depend on the history of the game, so they are left to the search.
 
         // Draw by stalemate.
         if white_analysis.king_safety == KingSafety::Stalemate
             || black_analysis.king_safety == KingSafety::Stalemate
         {
-            return Evaluation::Draw;
+            return 0;
+        }
+
+        // Draw by the fifty-move rule.
+        if state.is_fifty_move_draw() {
+            return 0;
         }
 
-        // Draw by the seventy-five-move rule.
-        if state.half_moves >= 75 {
-            return Evaluation::Draw;
+        // Draw by insufficient material.
+        if state.has_insufficient_material() {
+            return 0;
         }
 
         let mut white_score: f32 = 0.0;
@@ -3673,19 +4443,8 @@ impl Engine {
         }
 
         // Reward each side for the total amount for moves they can make.
-        let white_total_moves = white_analysis
-            .moves
-            .iter()
-            .filter_map(|entry| entry.as_ref())
-            .fold(0, |accumulator, entry| accumulator + entry.len())
-            as isize;
-
-        let black_total_moves = black_analysis
-            .moves
-            .iter()
-            .filter_map(|entry| entry.as_ref())
-            .fold(0, |accumulator, entry| accumulator + entry.len())
-            as isize;
+        let white_total_moves = white_analysis.moves.len() as isize;
+        let black_total_moves = black_analysis.moves.len() as isize;
 
         white_score += (white_total_moves * 2 - black_total_moves) as f32;
         black_score += (black_total_moves * 2 - white_total_moves) as f32;
@@ -3738,543 +4497,627 @@ impl Engine {
         white_score -= (white_total_initial_ranks * 7) as f32;
         black_score -= (black_total_initial_ranks * 7) as f32;
 
-        // Penalize each side if the amount of half moves is approaching fifty moves.
-        // TODO(thismarvin): Test out whether or not these weights make any sense.
-        let half_moves_penalty_multiplier = match state.half_moves {
-            0..=10 => 0,
-            11..=25 => 1,
-            26..=40 => 4,
-            41..=45 => 8,
-            _ => 16,
-        };
-
-        let half_moves_penalty = state.half_moves * half_moves_penalty_multiplier;
-
-        white_score -= half_moves_penalty as f32;
-        black_score -= half_moves_penalty as f32;
-
-        // The final evaluation is simply the difference in white and black score.
-        Evaluation::Static((white_score - black_score).round() as i16)
-    }
-
-    // TODO(thismarvin): Is it possible to combine this with `minimax`?
-    fn quiescence_minimax(params: &mut MinimaxParams, analysis: Analysis) -> SearchNode {
-        let opponent = params.state.side_to_move.opponent();
-
-        let mut needs_sorting = false;
-        let mut moves = analysis
-            .moves
-            .iter()
-            .flatten()
-            .flatten()
-            .map(|lan| {
-                let score: u16 = match params.state.board[lan.end] {
-                    // Score captures higher.
-                    Some(Piece(color, kind)) if color == opponent => {
-                        needs_sorting = true;
-
-                        let start = params.state.board[lan.start]
-                            .expect("This should always be a Some Piece.");
-
-                        match kind {
-                            // Evaluate capturing with a king last.
-                            PieceKind::King => 1,
-                            // Prefer capturing with pieces with the least value.
-                            _ => (900 + kind.value() - start.1.value()) as u16,
-                        }
-                    }
-                    _ => 0,
-                };
-
-                (score, lan)
-            })
-            .collect::<Vec<(u16, &Lan)>>();
-
-        if needs_sorting {
-            moves.sort_by(|a, b| b.0.cmp(&a.0));
-        }
-
-        let moves = moves;
-
-        let mut alpha = params.alpha;
-        let mut beta = params.beta;
-        let mut evaluation = match params.state.side_to_move {
-            Color::White => Evaluation::Static(i16::MIN),
-            Color::Black => Evaluation::Static(i16::MAX),
-        };
-        let mut best_lan: Option<Lan> = None;
-        let mut best_child: Option<SearchNode> = None;
-
-        for (_, &lan) in moves {
-            (*params.searched) += 1;
-
-            let undoer = params
-                .state
-                .make_move(lan)
-                .expect("The given move should always be valid.");
-
-            let mut next = MinimaxParams {
-                state: params.state,
-                depth: params.depth,
-                searched: params.searched,
-                line: params.line,
-                alpha,
-                beta,
-                strategy: params.strategy.opposite(),
-            };
-
-            let node = Engine::quiescence(&mut next);
+        // Penalize each side if the amount of half moves is approaching fifty moves.
+        // TODO(thismarvin): Test out whether or not these weights make any sense.
+        let half_moves_penalty_multiplier = match state.half_moves {
+            0..=10 => 0,
+            11..=25 => 1,
+            26..=40 => 4,
+            41..=45 => 8,
+            _ => 16,
+        };
 
-            params.state.unmake_move(undoer);
+        let half_moves_penalty = state.half_moves * half_moves_penalty_multiplier;
 
-            let score = i16::from(node.evaluation);
+        white_score -= half_moves_penalty as f32;
+        black_score -= half_moves_penalty as f32;
 
-            match params.strategy {
-                Strategy::Maximizing => {
-                    evaluation = evaluation.max(node.evaluation);
+        // The final evaluation is simply the difference in white and black score.
+        relative((white_score - black_score).round() as i16)
+    }
 
-                    if score > alpha {
-                        alpha = score;
-                        best_lan = Some(lan);
-                        best_child = Some(node);
-                    }
-                }
-                Strategy::Minimizing => {
-                    evaluation = evaluation.min(node.evaluation);
+    /// Scores how promising a move looks before it is searched; captures are scored the highest.
+    fn score_move(state: &State, mv: Move) -> u16 {
+        match state.board[mv.end()] {
+            Some(Piece(_, kind)) => {
+                let start = state.board[mv.start()].expect("This should always be a Some Piece.");
 
-                    if score < beta {
-                        beta = score;
-                        best_lan = Some(lan);
-                        best_child = Some(node);
-                    }
+                match kind {
+                    // Evaluate capturing with a king last.
+                    PieceKind::King => 1,
+                    // Prefer capturing with pieces with the least value.
+                    _ => (900 + kind.value() - start.1.value()) as u16,
                 }
             }
+            None => 0,
+        }
+    }
 
-            if beta <= alpha {
-                break;
+    /// Swaps the highest scoring move that has yet to be searched into the given index, and
+    /// returns it. Moves are picked one at a time because a cutoff usually happens long before
+    /// the whole list would have needed to be sorted.
+    fn select_move(moves: &mut MoveList, scores: &mut [i32; MAX_MOVES], index: usize) -> Move {
+        let mut best = index;
+
+        for i in (index + 1)..moves.len() {
+            if scores[i] > scores[best] {
+                best = i;
             }
         }
 
-        let transformation = best_lan;
-        let child = best_child.map(Box::new);
+        moves.swap(index, best);
+        scores.swap(index, best);
+
+        moves[index]
+    }
 
-        SearchNode {
-            evaluation,
-            transformation,
-            child,
+    /// Mate scores are relative to the root of the search, but the transposition table is shared
+    /// between searches; store them relative to the State itself instead.
+    fn score_to_table(score: i16, ply: usize) -> i16 {
+        // A mate can never be further away than MAX_PLY, so neither can the stored score.
+        if score >= MATE_BOUND {
+            score.saturating_add(ply as i16).min(CHECKMATE_EVALUATION)
+        } else if score <= -MATE_BOUND {
+            score.saturating_sub(ply as i16).max(-CHECKMATE_EVALUATION)
+        } else {
+            score
         }
     }
 
-    fn quiescence(params: &mut MinimaxParams) -> SearchNode {
-        let analysis = params.state.analyze(params.state.side_to_move);
+    /// Reverses `score_to_table`.
+    fn score_from_table(score: i16, ply: usize) -> i16 {
+        if score >= MATE_BOUND {
+            (score - ply as i16).max(MATE_BOUND)
+        } else if score <= -MATE_BOUND {
+            (score + ply as i16).min(-MATE_BOUND)
+        } else {
+            score
+        }
+    }
 
-        match analysis.king_safety {
-            KingSafety::Checkmate => {
-                let evaluation = Evaluation::Winner(params.state.side_to_move.opponent());
+    /// Evaluates the State like `evaluate`, except that a checkmate is scored by how far away it is
+    /// from the root, just like the search does.
+    fn evaluate_at(state: State, ply: usize) -> i16 {
+        match Engine::evaluate(state) {
+            score if score >= MATE_BOUND => CHECKMATE_EVALUATION - ply as i16,
+            score if score <= -MATE_BOUND => -CHECKMATE_EVALUATION + ply as i16,
+            score => score,
+        }
+    }
 
-                return SearchNode {
-                    evaluation,
-                    transformation: None,
-                    child: None,
-                };
-            }
-            KingSafety::Stalemate => {
-                return SearchNode {
-                    evaluation: Evaluation::Draw,
-                    transformation: None,
-                    child: None,
-                };
-            }
-            KingSafety::Check => {
-                return Engine::quiescence_minimax(params, analysis);
-            }
-            _ => (),
+    /// Searches captures until the State is quiet, so that the static evaluation is not fooled by
+    /// a piece that is about to be taken back. Every move is searched while in check instead.
+    fn quiescence(params: &mut SearchParams, ply: usize, mut alpha: i16, beta: i16) -> i16 {
+        if params.limits.should_abort(params.searched) {
+            return 0;
         }
 
-        let mut alpha = params.alpha;
-        let mut beta = params.beta;
-        let mut evaluation = match params.state.side_to_move {
-            Color::White => Evaluation::Static(i16::MIN),
-            Color::Black => Evaluation::Static(i16::MAX),
-        };
+        if ply >= MAX_PLY - 1 {
+            return Engine::evaluate_at(*params.state, ply);
+        }
+
+        let mut analysis = params
+            .state
+            .analyze(params.state.side_to_move, MoveFilter::Captures);
 
-        let standing_pat = Engine::evaluate(*params.state);
-        let score = i16::from(standing_pat);
+        // Getting out of check (or finding out that it is checkmate) requires the quiet moves as
+        // well.
+        let in_check = analysis.king_safety == KingSafety::Check;
 
-        match params.strategy {
-            Strategy::Maximizing => {
-                evaluation = evaluation.max(standing_pat);
-                alpha = alpha.max(score);
+        if in_check {
+            let quiets = params
+                .state
+                .analyze(params.state.side_to_move, MoveFilter::Quiets)
+                .moves;
+
+            for &mv in quiets.iter() {
+                analysis.moves.push(mv);
             }
-            Strategy::Minimizing => {
-                evaluation = evaluation.min(standing_pat);
-                beta = beta.min(score);
+
+            if analysis.moves.is_empty() {
+                return -CHECKMATE_EVALUATION + ply as i16;
             }
         }
 
-        if beta <= alpha {
-            return SearchNode {
-                evaluation,
-                transformation: None,
-                child: None,
-            };
-        }
+        // Stalemate does not need to be handled here; `evaluate` already scores it as a draw.
 
-        let mut moves = analysis
-            .moves
-            .iter()
-            .flatten()
-            .flatten()
-            .filter(|lan| params.state.board[lan.end].is_some())
-            .map(|lan| {
-                let score: u16 = match params.state.board[lan.end] {
-                    // Score captures higher.
-                    Some(Piece(_, kind)) => {
-                        let start = params.state.board[lan.start]
-                            .expect("This should always be a Some Piece.");
-
-                        match kind {
-                            // Evaluate capturing with a king last.
-                            PieceKind::King => 1,
-                            // Prefer capturing with pieces with the least value.
-                            _ => (900 + kind.value() - start.1.value()) as u16,
-                        }
-                    }
-                    _ => unreachable!(),
-                };
+        let mut best_score = -i16::MAX;
 
-                (score, lan)
-            })
-            .collect::<Vec<(u16, &Lan)>>();
+        // Unless in check, the side to move can always decline to capture anything.
+        if !in_check {
+            let standing_pat = Engine::evaluate(*params.state);
 
-        if moves.is_empty() {
-            return SearchNode {
-                evaluation,
-                transformation: None,
-                child: None,
-            };
+            if standing_pat >= beta {
+                return standing_pat;
+            }
+
+            alpha = alpha.max(standing_pat);
+            best_score = standing_pat;
         }
 
-        moves.sort_by(|a, b| b.0.cmp(&a.0));
+        let mut moves = analysis.moves;
+        let mut scores = [0; MAX_MOVES];
+
+        for (i, &mv) in moves.iter().enumerate() {
+            scores[i] = Engine::score_move(params.state, mv) as i32;
+        }
 
-        let moves = moves;
+        for i in 0..moves.len() {
+            let mv = Engine::select_move(&mut moves, &mut scores, i);
 
-        let mut best_lan: Option<Lan> = None;
-        let mut best_child: Option<SearchNode> = None;
+            // A capture that loses material is very unlikely to improve on standing pat.
+            if !in_check && params.state.static_exchange(mv) < 0 {
+                continue;
+            }
 
-        for (_, &lan) in moves {
-            (*params.searched) += 1;
+            params.searched += 1;
 
             let undoer = params
                 .state
-                .make_move(lan)
+                .make_move(mv)
                 .expect("The given move should always be valid.");
 
-            let mut next = MinimaxParams {
-                state: params.state,
-                depth: params.depth,
-                searched: params.searched,
-                line: params.line,
-                alpha,
-                beta,
-                strategy: params.strategy.opposite(),
-            };
-
-            let node = Engine::quiescence(&mut next);
+            let score = -Engine::quiescence(params, ply + 1, -beta, -alpha);
 
             params.state.unmake_move(undoer);
 
-            let score = i16::from(node.evaluation);
+            // The result of an interrupted search cannot be trusted.
+            if params.limits.should_abort(params.searched) {
+                break;
+            }
 
-            match params.strategy {
-                Strategy::Maximizing => {
-                    evaluation = evaluation.max(node.evaluation);
+            best_score = best_score.max(score);
+            alpha = alpha.max(score);
 
-                    if score > alpha {
-                        alpha = score;
-                        best_lan = Some(lan);
-                        best_child = Some(node);
-                    }
-                }
-                Strategy::Minimizing => {
-                    evaluation = evaluation.min(node.evaluation);
+            if alpha >= beta {
+                break;
+            }
+        }
 
-                    if score < beta {
-                        beta = score;
-                        best_lan = Some(lan);
-                        best_child = Some(node);
-                    }
-                }
+        best_score
+    }
+
+    /// Searches the current State with principal variation search, and returns its score relative
+    /// to the side to move.
+    ///
+    /// The first move is assumed to be the best one, so every other move is only searched with a
+    /// null window to prove that it is not any better. The rare move that proves otherwise is
+    /// searched again with the full window.
+    ///
+    /// Learn more about principal variation search here: https://www.chessprogramming.org/Principal_Variation_Search
+    fn negamax(
+        params: &mut SearchParams,
+        depth: u8,
+        ply: usize,
+        mut alpha: i16,
+        mut beta: i16,
+    ) -> i16 {
+        params.pv.clear(ply);
+
+        if params.limits.should_abort(params.searched) {
+            return 0;
+        }
+
+        if ply > 0 {
+            if Engine::is_repetition(params.state, params.history, params.root)
+                || params.state.has_insufficient_material()
+            {
+                return 0;
             }
 
-            if beta <= alpha {
-                break;
+            // Mate distance pruning: there is no point in looking for a mate that is further away
+            // than a mate that was already found.
+            alpha = alpha.max(-CHECKMATE_EVALUATION + ply as i16);
+            beta = beta.min(CHECKMATE_EVALUATION - ply as i16 - 1);
+
+            if alpha >= beta {
+                return alpha;
             }
         }
 
-        let transformation = best_lan;
-        let child = best_child.map(Box::new);
+        // Check extension: a check has to be answered, so it should never be left to quiescence.
+        // A long series of checks (e.g. a perpetual check) would otherwise be extended forever.
+        let in_check = params.state.is_in_check();
+        let depth = match in_check && ply < 2 * params.depth as usize {
+            true => depth.saturating_add(1),
+            false => depth,
+        };
 
-        SearchNode {
-            evaluation,
-            transformation,
-            child,
+        if depth == 0 {
+            return Engine::quiescence(params, ply, alpha, beta);
         }
-    }
 
-    fn minimax(params: &mut MinimaxParams) -> SearchNode {
-        if params.depth == 0 {
-            return Engine::quiescence(params);
+        if ply >= MAX_PLY - 1 {
+            return Engine::evaluate_at(*params.state, ply);
         }
 
-        let opponent = params.state.side_to_move.opponent();
-        let analysis = params.state.analyze(params.state.side_to_move);
+        // Only the principal variation is searched with a window wider than a null window.
+        let pv_node = beta > alpha + 1;
+        let entry = params.table.probe(params.state.hash);
 
-        match analysis.king_safety {
-            KingSafety::Checkmate => {
-                let evaluation = Evaluation::Winner(params.state.side_to_move.opponent());
+        // The root always needs to be searched in order to suggest a move, and cutting off the
+        // principal variation would leave it incomplete.
+        if let (Some(entry), false) = (entry, pv_node || ply == 0) {
+            let score = Engine::score_from_table(entry.score, ply);
 
-                return SearchNode {
-                    evaluation,
-                    transformation: None,
-                    child: None,
-                };
-            }
-            KingSafety::Stalemate => {
-                return SearchNode {
-                    evaluation: Evaluation::Draw,
-                    transformation: None,
-                    child: None,
+            let usable = entry.depth >= depth
+                && match entry.bound {
+                    Bound::Exact => true,
+                    Bound::Lower => score >= beta,
+                    Bound::Upper => score <= alpha,
                 };
+
+            if usable {
+                return score;
             }
+        }
+
+        let analysis = params
+            .state
+            .analyze(params.state.side_to_move, MoveFilter::All);
+
+        match analysis.king_safety {
+            KingSafety::Checkmate => return -CHECKMATE_EVALUATION + ply as i16,
+            KingSafety::Stalemate => return 0,
             _ => (),
         }
 
-        // TODO(thismarvin): There has to be a better way to incorporate the previous search...
-        let target = if let Some(line) = params.line {
-            line.get(line.len() + 1 - params.depth as usize)
-        } else {
-            None
-        };
-        let mut pivot = None;
+        // Checkmate takes precedence over the fifty-move rule, so this has to wait until now.
+        if ply > 0 && params.state.is_fifty_move_draw() {
+            return 0;
+        }
 
-        // `minimax` should be faster when the best moves are searched first.
-        let mut needs_sorting = false;
-        let mut moves = analysis
-            .moves
-            .iter()
-            .flatten()
-            .flatten()
-            .enumerate()
-            .map(|(i, lan)| {
-                if let Some(target) = target {
-                    if *lan == *target {
-                        pivot = Some(i);
-
-                        return (u16::MAX, lan);
-                    }
-                }
+        // Null-move pruning: if passing the turn still fails high after a reduced search, then
+        // actually moving is very likely to fail high as well. This falls apart in check (passing
+        // is illegal), in pawn endgames (where zugzwang is common), and after another null move
+        // (two passes in a row just waste a search).
+        let previous_null = ply > 0 && params.played[ply - 1].is_none();
 
-                let score: u16 = match params.state.board[lan.end] {
-                    // Score captures higher.
-                    Some(Piece(color, kind)) if color == opponent => {
-                        needs_sorting = true;
+        if !pv_node
+            && depth >= NULL_MOVE_MIN_DEPTH
+            && !previous_null
+            && !in_check
+            && params
+                .state
+                .board
+                .has_non_pawn_material(params.state.side_to_move)
+            && Engine::evaluate(*params.state) >= beta
+        {
+            let reduction = match depth > 6 {
+                true => 3,
+                false => 2,
+            };
 
-                        let start = params.state.board[lan.start]
-                            .expect("This should always be a Some Piece.");
+            params.played[ply] = None;
+            params.history.push(params.state.hash);
 
-                        match kind {
-                            // Evaluate capturing with a king last.
-                            PieceKind::King => 1,
-                            // Prefer capturing with pieces with the least value.
-                            _ => (900 + kind.value() - start.1.value()) as u16,
-                        }
-                    }
-                    _ => 0,
-                };
+            let undoer = params.state.make_null_move();
+            let score = -Engine::negamax(
+                params,
+                depth.saturating_sub(1 + reduction),
+                ply + 1,
+                -beta,
+                -beta + 1,
+            );
 
-                (score, lan)
-            })
-            .collect::<Vec<(u16, &Lan)>>();
+            params.state.unmake_null_move(undoer);
+            params.history.pop();
+
+            if params.limits.should_abort(params.searched) {
+                return 0;
+            }
 
-        // Evaluate the previous best move at this depth first.
-        if let Some(pivot) = pivot {
-            moves.swap(0, pivot);
+            // Passing is never actually legal, so only the bound itself can be trusted.
+            if score >= beta {
+                return beta;
+            }
         }
 
-        if needs_sorting {
-            moves.sort_by(|a, b| b.0.cmp(&a.0));
+        // The best move from the transposition table (usually from the previous iteration) is the
+        // most likely to be the best move again.
+        let target = entry.and_then(|entry| entry.best);
+
+        let side = params.state.side_to_move;
+        let previous = ply.checked_sub(1).and_then(|ply| params.played[ply]);
+
+        // The search should be faster when the best moves are searched first.
+        let mut moves = analysis.moves;
+        let mut scores = [0; MAX_MOVES];
+
+        for (i, &mv) in moves.iter().enumerate() {
+            scores[i] = if Some(mv) == target {
+                i32::MAX
+            } else if mv.is_capture() {
+                match params.state.static_exchange(mv) {
+                    exchange if exchange >= 0 => {
+                        CAPTURE_ORDER + Engine::score_move(params.state, mv) as i32
+                    }
+                    exchange => -CAPTURE_ORDER + exchange as i32,
+                }
+            } else {
+                params.quiet_history.score(side, mv, ply, previous)
+            };
         }
 
-        let moves = moves;
+        let original_alpha = alpha;
+        let mut best_score = -i16::MAX;
+        let mut best_move: Option<Move> = None;
+        let mut quiets = 0;
+        // The quiet moves that were searched without causing a cutoff.
+        let mut failed = MoveList::new();
 
-        let mut alpha = params.alpha;
-        let mut beta = params.beta;
-        let mut evaluation = match params.state.side_to_move {
-            Color::White => Evaluation::Static(i16::MIN),
-            Color::Black => Evaluation::Static(i16::MAX),
-        };
-        let mut best_lan: Option<Lan> = None;
-        let mut best_child: Option<SearchNode> = None;
+        for i in 0..moves.len() {
+            let mv = Engine::select_move(&mut moves, &mut scores, i);
+            let quiet = !mv.is_capture() && mv.promotion().is_none();
 
-        for (_, &lan) in moves {
-            (*params.searched) += 1;
+            if quiet {
+                quiets += 1;
+            }
 
+            // Late move pruning: near the horizon, quiet moves that were ordered this late are
+            // unlikely to be worth searching at all. Checks are left alone, and so is every move
+            // until one of them has been found to avoid getting mated.
+            let prunable = params.prune_late_moves
+                && !pv_node
+                && !in_check
+                && quiet
+                && (depth as usize) < LATE_MOVE_PRUNING.len()
+                && quiets > LATE_MOVE_PRUNING[depth as usize]
+                && best_score > -MATE_BOUND;
+
+            let hash = params.state.hash;
             let undoer = params
                 .state
-                .make_move(lan)
+                .make_move(mv)
                 .expect("The given move should always be valid.");
 
-            let mut next = MinimaxParams {
-                state: params.state,
-                depth: params.depth - 1,
-                searched: params.searched,
-                line: params.line,
-                alpha,
-                beta,
-                strategy: params.strategy.opposite(),
-            };
+            if prunable && !params.state.is_in_check() {
+                params.state.unmake_move(undoer);
+                continue;
+            }
 
-            let node = Engine::minimax(&mut next);
+            params.searched += 1;
 
-            params.state.unmake_move(undoer);
+            params.played[ply] = Some(mv);
+            params.history.push(hash);
+
+            let score = if i == 0 {
+                -Engine::negamax(params, depth - 1, ply + 1, -beta, -alpha)
+            } else {
+                // Late move reductions: quiet moves that were ordered this late are searched less
+                // deeply, unless they turn out to be better than expected.
+                let reduction = if params.prune_late_moves
+                    && depth >= LMR_MIN_DEPTH
+                    && i >= LMR_MIN_INDEX
+                    && quiet
+                    && !in_check
+                {
+                    let reduction = late_move_reductions()
+                        [(depth as usize).min(MAX_SEARCH_DEPTH as usize)][i]
+                        .saturating_sub(pv_node as u8);
 
-            let score = i16::from(node.evaluation);
+                    // The reduced search should never drop straight into quiescence.
+                    reduction.min(depth - 2)
+                } else {
+                    0
+                };
 
-            match params.strategy {
-                Strategy::Maximizing => {
-                    evaluation = evaluation.max(node.evaluation);
+                let mut score =
+                    -Engine::negamax(params, depth - 1 - reduction, ply + 1, -alpha - 1, -alpha);
 
-                    if score > alpha {
-                        alpha = score;
-                        best_lan = Some(lan);
-                        best_child = Some(node);
-                    }
+                if reduction > 0 && score > alpha {
+                    score = -Engine::negamax(params, depth - 1, ply + 1, -alpha - 1, -alpha);
                 }
-                Strategy::Minimizing => {
-                    evaluation = evaluation.min(node.evaluation);
 
-                    if score < beta {
-                        beta = score;
-                        best_lan = Some(lan);
-                        best_child = Some(node);
-                    }
+                if score > alpha && score < beta {
+                    -Engine::negamax(params, depth - 1, ply + 1, -beta, -alpha)
+                } else {
+                    score
                 }
+            };
+
+            params.state.unmake_move(undoer);
+            params.history.pop();
+
+            // The result of an interrupted search cannot be trusted.
+            if params.limits.should_abort(params.searched) {
+                break;
+            }
+
+            if score > best_score {
+                best_score = score;
+            }
+
+            if score > alpha {
+                alpha = score;
+                best_move = Some(mv);
+                params.pv.update(ply, mv);
             }
 
-            if beta <= alpha {
+            if alpha >= beta {
+                if quiet {
+                    params
+                        .quiet_history
+                        .update(side, mv, ply, previous, depth, &failed);
+                }
+
                 break;
             }
+
+            if quiet {
+                failed.push(mv);
+            }
+        }
+
+        if !params.limits.aborted.get() {
+            let bound = if best_score <= original_alpha {
+                Bound::Upper
+            } else if best_score >= beta {
+                Bound::Lower
+            } else {
+                Bound::Exact
+            };
+
+            params.table.store(TableEntry {
+                hash: params.state.hash,
+                depth,
+                bound,
+                score: Engine::score_to_table(best_score, ply),
+                best: best_move.or(target),
+                generation: 0,
+            });
+        }
+
+        best_score
+    }
+
+    /// Continues a principal variation that was cut short by the transposition table.
+    fn extend_line(mut state: State, line: &mut Vec<Lan>, table: &TranspositionTable, depth: u8) {
+        if Engine::make_sequence(&mut state, line).is_err() {
+            return;
         }
 
-        let transformation = best_lan;
-        let child = best_child.map(Box::new);
+        while line.len() < depth as usize {
+            let lan = match table.probe(state.hash).and_then(|entry| entry.best) {
+                Some(mv) => Lan::from(mv),
+                None => break,
+            };
+
+            // Different States can share the same hash; make sure the move is actually legal.
+            if Engine::make_sequence(&mut state, &[lan]).is_err() {
+                break;
+            }
 
-        SearchNode {
-            evaluation,
-            transformation,
-            child,
+            line.push(lan);
         }
     }
 
     fn analyze(
         state: &mut State,
         depth: u8,
-        line: Option<Vec<Lan>>,
-    ) -> (Evaluation, InfoStatistics) {
+        history: &mut Vec<u64>,
+        limits: &SearchLimits,
+        table: &mut TranspositionTable,
+        quiet_history: &mut QuietHistory,
+    ) -> Option<(i16, InfoStatistics)> {
         if depth == 0 {
             panic!("Depth should never be zero.");
         }
 
-        let mut searched = 0;
-        let strategy = Strategy::from(state.side_to_move);
-
-        let mut params = MinimaxParams {
+        let mut params = SearchParams {
             state,
+            searched: 0,
+            root: history.len(),
+            history,
+            limits,
+            table,
+            pv: PrincipalVariation::new(),
+            quiet_history,
+            played: [None; MAX_PLY],
             depth,
-            searched: &mut searched,
-            line: &line,
-            alpha: i16::MIN,
-            beta: i16::MAX,
-            strategy,
+            prune_late_moves: true,
         };
 
-        let result = Engine::minimax(&mut params);
-
-        let evaluation = result.evaluation;
-
-        // If the head of the search does not have a child then the game is over.
-        if result.child.is_none() {
-            match evaluation {
-                Evaluation::Winner(_) => {
-                    return (
-                        evaluation,
-                        InfoStatistics {
-                            depth: Some(0),
-                            score: Some(Score::Mate(0)),
-                            ..Default::default()
-                        },
-                    )
-                }
-                Evaluation::Draw => {
-                    return (
-                        evaluation,
-                        InfoStatistics {
-                            depth: Some(0),
-                            score: Some(Score::Cp(0)),
-                            ..Default::default()
-                        },
-                    )
-                }
-                _ => (),
-            }
-        }
-
-        let lan = result
-            .transformation
-            .expect("There should always be a move suggestion.");
-        let mut line: Vec<Lan> = Vec::with_capacity(depth as usize);
-
-        line.push(lan);
+        // `i16::MIN` cannot be negated, so the window is kept symmetric instead.
+        let score = Engine::negamax(&mut params, depth, 0, -i16::MAX, i16::MAX);
 
-        let mut head = result.child;
+        if limits.aborted.get() {
+            return None;
+        }
 
-        while let Some(contents) = head {
-            if let Some(lan) = contents.transformation {
-                line.push(lan);
-            }
+        let searched = params.searched;
+        let mut line = params
+            .pv
+            .line()
+            .iter()
+            .map(|&mv| Lan::from(mv))
+            .collect::<Vec<Lan>>();
+
+        // If the root of the search does not have a line then the game is over.
+        if line.is_empty() {
+            let info = InfoStatistics {
+                depth: Some(0),
+                score: Some(match score {
+                    0 => Score::Cp(0),
+                    _ => Score::Mate(0),
+                }),
+                ..Default::default()
+            };
 
-            head = contents.child;
+            return Some((score, info));
         }
 
-        let score = match evaluation {
-            Evaluation::Winner(side) => {
+        Engine::extend_line(*state, &mut line, table, depth);
+
+        let reported = match score.abs() {
+            magnitude if magnitude >= MATE_BOUND => {
                 // "If the engine is getting mated use negative values for y."
-                let sign = if state.side_to_move != side { -1 } else { 1 };
+                let sign = score.signum() as i8;
 
                 // Convert plies to moves.
-                let moves = (line.len() as f32 / 2.0).ceil() as i8 * sign;
+                let plies = CHECKMATE_EVALUATION - magnitude;
+                let moves = ((plies + 1) / 2) as i8 * sign;
 
                 Score::Mate(moves)
             }
-            _ => Score::Cp(i16::from(evaluation)),
+            _ => Score::Cp(score),
         };
 
-        (
-            evaluation,
+        Some((
+            score,
             InfoStatistics {
                 depth: Some(depth),
                 nodes: Some(searched),
                 pv: Some(line),
-                score: Some(score),
+                score: Some(reported),
                 ..Default::default()
             },
-        )
+        ))
+    }
+}
+
+/// A handle that can interrupt a search that is in progress, even from another thread.
+#[derive(Debug, Clone)]
+pub struct StopHandle(Arc<AtomicBool>);
+
+impl StopHandle {
+    pub fn stop(&self) {
+        self.0.store(true, atomic::Ordering::Relaxed);
+    }
+}
+
+/// An evaluation Pescado made before suggesting a move.
+#[derive(Debug, Clone, Copy)]
+struct Annotation {
+    /// The amount of moves that were played before the search.
+    ply: usize,
+    lan: Lan,
+    score: Score,
+    depth: u8,
+}
+
+impl From<Annotation> for String {
+    fn from(value: Annotation) -> Self {
+        match value.score {
+            Score::Cp(cp) => format!("{:+.2}/{}", cp as f32 / 100.0, value.depth),
+            Score::Mate(mate) if mate < 0 => format!("-M{}/{}", -mate, value.depth),
+            Score::Mate(mate) => format!("+M{}/{}", mate, value.depth),
+        }
     }
 }
 
 pub struct Pescado {
+    /// The State the current game started from.
+    start: State,
+    /// Every move that was played since `start`.
+    moves: Vec<Lan>,
     state: State,
+    /// The hashes of every State that was played before the current one.
+    history: Vec<u64>,
+    annotations: Vec<Annotation>,
+    table: TranspositionTable,
+    quiet_history: Box<QuietHistory>,
+    stop: Arc<AtomicBool>,
     cb: Box<dyn Fn(String)>,
 }
 
@@ -4284,48 +5127,237 @@ impl Pescado {
         F: Fn(String) + 'static,
     {
         Pescado {
+            start: State::default(),
+            moves: Vec::new(),
             state: State::default(),
+            history: Vec::new(),
+            annotations: Vec::new(),
+            table: TranspositionTable::default(),
+            quiet_history: Box::default(),
+            stop: Arc::new(AtomicBool::new(false)),
             cb: Box::new(callback),
         }
     }
 
-    fn go_depth(&mut self, depth: u8) {
+    /// Returns a handle that interrupts the current search.
+    ///
+    /// Stopping is meant to be paired with sending `stop` afterwards: an interrupted search still
+    /// reports its best move, and the stop request is only cleared once `stop` is processed. This
+    /// way a `stop` that arrives before its `go` command begins is not lost.
+    pub fn stop_handle(&self) -> StopHandle {
+        StopHandle(Arc::clone(&self.stop))
+    }
+
+    /// Forgets everything learned from previous searches, so that the next search does not depend
+    /// on them.
+    fn new_game(&mut self) {
+        self.table.clear();
+        self.quiet_history.clear();
+        self.annotations.clear();
+    }
+
+    /// Plays the given moves from the given State; the moves must already be known to be legal.
+    fn set_position(&mut self, start: State, moves: Vec<Lan>) {
+        self.start = start;
+        self.state = start;
+        self.history = Engine::make_sequence(&mut self.state, &moves)
+            .expect("The moves should have already been validated.");
+        self.moves = moves;
+    }
+
+    /// Searches the current State, and returns the suggested move along with the total amount of
+    /// nodes that were searched.
+    fn go_depth(&mut self, depth: u8, limits: GoLimits) -> (Suggestion, u128) {
         if depth == 0 {
             // TODO(thismarvin): Should zero just make the engine search forever?
             (self.cb)(String::from("Error: Unsupported depth"));
-            return;
+
+            let suggestion = Suggestion {
+                lan: None,
+                ponder: None,
+            };
+
+            return (suggestion, 0);
         }
 
-        let mut line = None;
+        let start = utils::timestamp();
+        let mut searched = 0;
+        let mut line: Option<Vec<Lan>> = None;
+        let mut report: Option<(Score, u8)> = None;
+
+        self.table.age();
 
         // Iterative Deepening.
         for i in 1..=depth {
-            let (evaluation, info) = Engine::analyze(&mut self.state, i, line);
+            // The first iteration is always allowed to finish (unless the host says otherwise), so
+            // that there is at least one scored move to suggest.
+            let limited = i > 1;
+
+            let search_limits = SearchLimits {
+                stop: Some(self.stop.as_ref()),
+                deadline: limits
+                    .budget
+                    .filter(|_| limited)
+                    .map(|budget| start + budget.hard),
+                nodes: limits
+                    .nodes
+                    .filter(|_| limited)
+                    .map(|nodes| nodes.saturating_sub(searched)),
+                ..Default::default()
+            };
+
+            let (score, mut info) = match Engine::analyze(
+                &mut self.state,
+                i,
+                &mut self.history,
+                &search_limits,
+                &mut self.table,
+                &mut self.quiet_history,
+            ) {
+                Some(result) => result,
+                None => break,
+            };
+
+            searched += info.nodes.unwrap_or(0);
+
+            let elapsed = utils::timestamp().saturating_sub(start);
+
+            // Every iteration counts towards the nodes of the whole search.
+            info.nodes = Some(searched);
+            info.time = Some(elapsed);
+
+            if elapsed > 0 {
+                info.nps = Some((searched * 1000 / elapsed as u128) as u64);
+            }
+
+            (self.cb)(String::from(&info));
+
+            if let (Some(score), Some(depth)) = (info.score, info.depth) {
+                report = Some((score, depth));
+            }
+
+            line = info.pv;
+
+            // There is nothing left to find once the game is over or a forced mate was found.
+            if line.is_none() || score.abs() >= MATE_BOUND {
+                break;
+            }
+
+            // The next iteration is unlikely to finish in time; save the time for later moves.
+            if let Some(budget) = limits.budget {
+                if elapsed >= budget.soft {
+                    break;
+                }
+            }
+
+            if let Some(nodes) = limits.nodes {
+                if searched >= nodes {
+                    break;
+                }
+            }
+        }
+
+        // An infinite search must not suggest a move until the host says so, even if there is
+        // nothing left to search.
+        if limits.infinite {
+            self.wait_for_stop();
+        }
+
+        let suggestion = match line {
+            Some(pv) => Suggestion {
+                lan: Some(pv[0]),
+                ponder: pv.get(1).copied(),
+            },
+            // Even the first iteration was interrupted, but a move still has to be suggested if
+            // there is one; prefer whatever a previous search thought was best.
+            None => {
+                let moves = self.state.legal_moves();
+                let previous = self
+                    .table
+                    .probe(self.state.hash)
+                    .and_then(|entry| entry.best)
+                    .map(Lan::from)
+                    .filter(|lan| moves.contains(lan));
+
+                Suggestion {
+                    lan: previous.or(moves.first().copied()),
+                    ponder: None,
+                }
+            }
+        };
+
+        // Remember the evaluation so that it can be included when the game is exported.
+        if let (Some(lan), Some((score, depth))) = (suggestion.lan, report) {
+            let ply = self.moves.len();
+
+            self.annotations.retain(|annotation| annotation.ply != ply);
+            self.annotations.push(Annotation {
+                ply,
+                lan,
+                score,
+                depth,
+            });
+        }
+
+        (self.cb)(format!("{}", suggestion));
+
+        (suggestion, searched)
+    }
+
+    /// Exports the current game, including Pescado's evaluations (e.g. `{+0.35/12}`) for every
+    /// move it suggested that was actually played.
+    pub fn game(&self) -> pgn::Game {
+        let mut game = pgn::Game::default();
+
+        if self.start != State::default() {
+            game.set_tag("SetUp", "1");
+            game.set_tag("FEN", &Fen::from(self.start).to_string());
+        }
+
+        let mut state = self.start;
+        let mut nodes = Vec::with_capacity(self.moves.len());
+
+        for (ply, &lan) in self.moves.iter().enumerate() {
+            let mut node = pgn::Node::new(&state, lan)
+                .expect("Every move in the game should have already been validated.");
+
+            node.comment = self
+                .annotations
+                .iter()
+                .find(|annotation| annotation.ply == ply && annotation.lan == lan)
+                .map(|&annotation| String::from(annotation));
 
-            (self.cb)(String::from(&info));
+            nodes.push(node);
 
-            line = info.pv;
+            state
+                .play_unchecked(lan)
+                .expect("Every move in the game should have already been validated.");
+        }
 
-            match evaluation {
-                Evaluation::Winner(_) | Evaluation::Draw => {
-                    break;
-                }
-                _ => (),
-            }
+        // Nest every move within the one before it.
+        for mut node in nodes.into_iter().rev() {
+            node.children = std::mem::take(&mut game.moves);
+            game.moves = vec![node];
         }
 
-        let suggestion = match line {
-            Some(pv) => Suggestion {
-                lan: Some(pv[0]),
-                ponder: pv.get(1).copied(),
-            },
-            None => Suggestion {
-                lan: None,
-                ponder: None,
-            },
+        game.result = match state.outcome(&self.history) {
+            Outcome::Ongoing => pgn::GameResult::Unknown,
+            Outcome::Checkmate(Color::White) => pgn::GameResult::WhiteWins,
+            Outcome::Checkmate(Color::Black) => pgn::GameResult::BlackWins,
+            _ => pgn::GameResult::Draw,
         };
 
-        (self.cb)(format!("{}", suggestion));
+        game.set_tag("Result", &game.result.to_string());
+
+        game
+    }
+
+    fn wait_for_stop(&self) {
+        // Threads are not available on wasm, which is why `send` refuses `go infinite` there.
+        #[cfg(not(target_arch = "wasm32"))]
+        while !self.stop.load(atomic::Ordering::Relaxed) {
+            std::thread::sleep(std::time::Duration::from_millis(5));
+        }
     }
 
     fn go_perft(&mut self, depth: u8) {
@@ -4337,23 +5369,14 @@ impl Pescado {
 
         let mut string = String::new();
 
-        let analysis = self.state.analyze(self.state.side_to_move);
-        let moves = analysis.moves.iter().flatten().flatten();
+        let moves = suite::divide(&self.state, depth, &[])
+            .expect("A depth of at least one without a path should always be valid.");
 
         let mut total = 0;
 
-        for &lan in moves {
-            let undoer = self
-                .state
-                .make_move(lan)
-                .expect("The given move should always be valid.");
-
-            let perft = Engine::perft(&mut self.state, depth - 1);
-
+        for (lan, perft) in moves {
             total += perft;
 
-            self.state.unmake_move(undoer);
-
             string.push_str(&format!("{}: {}\n", lan, perft));
         }
 
@@ -4365,7 +5388,6 @@ impl Pescado {
 
     fn d(&self) {
         // TODO(thismarvin): Checkers field? (e.g. Checkers: e4)
-        // TODO(thismarvin): Key field? (e.g. Key: 8F8F01D4562F59FB)
 
         let mut string = String::new();
 
@@ -4402,18 +5424,24 @@ impl Pescado {
         let mut row = String::from(" ");
 
         for x in 0..BOARD_WIDTH {
-            row.push_str(format!(" {}  ", (b'a' + x as u8) as char).as_str());
+            row.push_str(format!(" {}  ", (b'a' + x) as char).as_str());
         }
 
         string.push_str(&row);
         string.push_str("\n\n");
-        string.push_str(&format!("Fen: {}", Fen::from(self.state)));
+        string.push_str(&format!("Fen: {}\n", Fen::from(self.state)));
+        string.push_str(&format!("Key: {:016X}", self.state.hash));
 
         (self.cb)(string);
     }
 
     fn flip(&mut self) {
-        self.state.side_to_move = self.state.side_to_move.opponent();
+        let (half_moves, full_moves) = (self.state.half_moves, self.state.full_moves);
+
+        // Only whose turn it is changes; the game clocks are left as they were.
+        self.state.make_null_move();
+        self.state.half_moves = half_moves;
+        self.state.full_moves = full_moves;
     }
 
     pub fn send(&mut self, command: &str) {
@@ -4424,22 +5452,70 @@ impl Pescado {
                 Command::Uci => {
                     (self.cb)("id name Pescado".to_string());
                     (self.cb)("id author the Pescado developers".to_string());
+                    (self.cb)(format!(
+                        "option name Hash type spin default {} min {} max {}",
+                        DEFAULT_HASH_SIZE, MIN_HASH_SIZE, MAX_HASH_SIZE
+                    ));
                     (self.cb)("uciok".to_string());
                 }
                 Command::Isready => {
                     (self.cb)("readyok".to_string());
                 }
-                Command::Position(state) => {
-                    self.state = state;
+                Command::Setoption(option) => match option {
+                    EngineOption::Hash(size) => {
+                        self.table = TranspositionTable::new(size);
+                    }
+                },
+                Command::Ucinewgame => {
+                    self.new_game();
+                }
+                Command::Position(start, moves) => {
+                    self.set_position(start, moves);
                 }
                 Command::Go(params) => match params {
-                    GoParams::Depth(depth) => {
-                        self.go_depth(depth);
+                    GoParams::Search(search) => {
+                        // A single threaded host has no way to send `stop` while the engine
+                        // waits for it, so an infinite search could never end.
+                        #[cfg(target_arch = "wasm32")]
+                        if search.infinite {
+                            (self.cb)(String::from(
+                                "Error: go infinite is not supported on this platform",
+                            ));
+
+                            return;
+                        }
+
+                        let side = self.state.side_to_move;
+
+                        // Both time limits can be given at once; the stricter one wins.
+                        let budget = [
+                            search.clock.map(|clock| TimeBudget::new(clock, side)),
+                            search.movetime.map(TimeBudget::fixed),
+                        ]
+                        .into_iter()
+                        .flatten()
+                        .reduce(|a, b| TimeBudget {
+                            soft: a.soft.min(b.soft),
+                            hard: a.hard.min(b.hard),
+                        });
+
+                        self.go_depth(
+                            search.depth.unwrap_or(MAX_SEARCH_DEPTH),
+                            GoLimits {
+                                budget,
+                                nodes: search.nodes,
+                                infinite: search.infinite,
+                            },
+                        );
                     }
                     GoParams::Perft(depth) => {
                         self.go_perft(depth);
                     }
                 },
+                Command::Stop => {
+                    // Any search the stop request was meant for has already finished.
+                    self.stop.store(false, atomic::Ordering::Relaxed);
+                }
                 Command::Quit => {}
                 Command::D => {
                     self.d();
@@ -4461,6 +5537,10 @@ impl Pescado {
 mod tests {
     use super::*;
 
+    fn to_lans(moves: &[Move]) -> Vec<Lan> {
+        moves.iter().map(|&mv| Lan::from(mv)).collect()
+    }
+
     #[test]
     fn test_coordinate_from_str() {
         let coordinate = Coordinate::try_from("a1a");
@@ -4556,6 +5636,72 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_move() -> Result<(), ChessError> {
+        let flags = [
+            MoveFlag::Quiet,
+            MoveFlag::DoublePush,
+            MoveFlag::KingCastle,
+            MoveFlag::QueenCastle,
+            MoveFlag::Capture,
+            MoveFlag::EnPassant,
+            MoveFlag::Promotion(PieceKind::Knight),
+            MoveFlag::Promotion(PieceKind::Queen),
+            MoveFlag::PromotionCapture(PieceKind::Bishop),
+            MoveFlag::PromotionCapture(PieceKind::Rook),
+        ];
+
+        for flag in flags {
+            let mv = Move::new(Coordinate::H1, Coordinate::A8, flag);
+
+            assert_eq!(mv.start(), Coordinate::H1);
+            assert_eq!(mv.end(), Coordinate::A8);
+            assert_eq!(mv.flag(), flag);
+        }
+
+        let mv = Move::new(
+            Coordinate::G7,
+            Coordinate::H8,
+            MoveFlag::PromotionCapture(PieceKind::Queen),
+        );
+
+        assert!(mv.is_capture());
+        assert_eq!(Lan::from(mv), Lan::try_from("g7h8q")?);
+
+        // A Lan needs the Board it is played on to tell what kind of Move it is.
+        let board = Board::from(Placement("r3k2r/1P6/8/3pP3/8/8/4P3/R3K2R".into()));
+        let encode = |lan: &str| -> Result<MoveFlag, ChessError> {
+            Ok(board.encode(Lan::try_from(lan)?)?.flag())
+        };
+
+        assert_eq!(encode("e2e3")?, MoveFlag::Quiet);
+        assert_eq!(encode("e2e4")?, MoveFlag::DoublePush);
+        assert_eq!(encode("e1g1")?, MoveFlag::KingCastle);
+        assert_eq!(encode("e8c8")?, MoveFlag::QueenCastle);
+        assert_eq!(encode("a1a8")?, MoveFlag::Capture);
+        assert_eq!(encode("e5d6")?, MoveFlag::EnPassant);
+        assert_eq!(encode("b7b8n")?, MoveFlag::Promotion(PieceKind::Knight));
+        assert_eq!(
+            encode("b7a8q")?,
+            MoveFlag::PromotionCapture(PieceKind::Queen)
+        );
+
+        assert!(encode("c3c4").is_err());
+        assert!(encode("a1a2q").is_err());
+        assert!(encode("b7b8k").is_err());
+
+        // Only a king on its home square can castle, and only a pawn next to an en passant target
+        // can change files without capturing.
+        let board = Board::from(Placement("8/8/8/8/3K4/8/P7/4k3".into()));
+
+        assert!(encode("e1g1").is_ok());
+        assert!(board.encode(Lan::try_from("d4f4")?).is_err());
+        assert!(board.encode(Lan::try_from("e1c1")?).is_err());
+        assert!(board.encode(Lan::try_from("a2b1")?).is_err());
+
+        Ok(())
+    }
+
     #[test]
     fn test_placement_from_str() {
         let placement = Placement::try_from("what is this really called?");
@@ -4706,6 +5852,68 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_epd_from_str() -> Result<(), ChessError> {
+        let epd = Epd::try_from(
+            "2rr3k/pp3pp1/1nnqbN1p/3pN3/2pP4/2P3Q1/PPB4P/R4RK1 w - - bm Qg6; id \"WAC.001\";",
+        )?;
+
+        assert_eq!(epd.id(), Some("WAC.001"));
+        assert_eq!(epd.best_moves()?, vec![Lan::try_from("g3g6")?]);
+        assert_eq!(epd.avoid_moves()?, vec![]);
+        assert_eq!(
+            State::from(epd),
+            State::from(Fen::try_from(
+                "2rr3k/pp3pp1/1nnqbN1p/3pN3/2pP4/2P3Q1/PPB4P/R4RK1 w - - 0 1"
+            )?)
+        );
+
+        let epd = Epd::try_from(
+            "r1b1k2r/ppppnppp/2n2q2/2b5/3NP3/2P1B3/PP3PPP/RN1QKB1R w KQkq - am Nxc6 Be2; bm Nb5 Qd2; acd 12; ce -35; c0 \"A comment; with a semicolon\"; hmvc 4; fmvn 7;",
+        )?;
+
+        assert_eq!(
+            epd.avoid_moves()?,
+            vec![Lan::try_from("d4c6")?, Lan::try_from("f1e2")?]
+        );
+        assert_eq!(
+            epd.best_moves()?,
+            vec![Lan::try_from("d4b5")?, Lan::try_from("d1d2")?]
+        );
+        assert_eq!(epd.analysis_depth(), Some(12));
+        assert_eq!(epd.evaluation(), Some(-35));
+        assert_eq!(epd.comment(), Some("A comment; with a semicolon"));
+        assert_eq!(
+            Fen::from(State::from(epd)).to_string(),
+            "r1b1k2r/ppppnppp/2n2q2/2b5/3NP3/2P1B3/PP3PPP/RN1QKB1R w KQkq - 4 7"
+        );
+
+        assert!(Epd::try_from("8/8/8/8 w - -").is_err());
+        assert!(Epd::try_from("4k3/8/8/8/8/8/8/4K3 w - - bm Kd2").is_err());
+        assert!(Epd::try_from("4k3/8/8/8/8/8/8/4K3 w - - id \"unterminated;").is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_epd_to_string() -> Result<(), ChessError> {
+        let epd = "2rr3k/pp3pp1/1nnqbN1p/3pN3/2pP4/2P3Q1/PPB4P/R4RK1 w - - bm Qg6; id \"WAC.001\";";
+
+        assert_eq!(Epd::try_from(epd)?.to_string(), epd);
+
+        let epd = "4k3/8/8/8/8/8/8/4K3 b - - c0 \"Kings only\"; acd 3; pv Kd7 Kd2;";
+
+        assert_eq!(Epd::try_from(epd)?.to_string(), epd);
+
+        // Extra whitespace is not preserved.
+        assert_eq!(
+            Epd::try_from("4k3/8/8/8/8/8/8/4K3 w - -   ce 0 ;")?.to_string(),
+            "4k3/8/8/8/8/8/8/4K3 w - - ce 0;"
+        );
+
+        Ok(())
+    }
+
     #[test]
     fn test_board_from_placement() -> Result<(), ChessError> {
         let board = Board::from(Placement(
@@ -4726,20 +5934,20 @@ mod tests {
         // Test moving nothing.
         let mut board = Board::default();
         let lan = Lan::try_from("e3e4")?;
-        let result = board.make_move(lan);
+        let result = board.encode(lan).and_then(|mv| board.make_move(mv));
         assert!(result.is_err());
 
         // Test promoting something other than a pawn.
         let mut board = Board::from(Placement("1k6/6R1/1K6/8/8/8/8/8".into()));
         let lan = Lan::try_from("g7g8q")?;
-        let result = board.make_move(lan);
+        let result = board.encode(lan).and_then(|mv| board.make_move(mv));
         assert!(result.is_err());
 
         // Test moving a piece.
         let mut board = Board::default();
         let lan = Lan::try_from("e2e4")?;
 
-        board.make_move(lan)?;
+        board.make_move(board.encode(lan)?)?;
 
         assert_eq!(board[Coordinate::E2], None);
         assert_eq!(
@@ -4751,7 +5959,7 @@ mod tests {
         let mut board = Board::from(Placement("8/2k1PK2/8/8/8/8/8/8".into()));
         let lan = Lan::try_from("e7e8q")?;
 
-        board.make_move(lan)?;
+        board.make_move(board.encode(lan)?)?;
 
         assert_eq!(board[Coordinate::E7], None);
         assert_eq!(
@@ -4763,7 +5971,7 @@ mod tests {
         let mut board = Board::from(Placement("4k3/8/8/8/4Pp2/8/8/4K3".into()));
         let lan = Lan::try_from("f4e3")?;
 
-        board.make_move(lan)?;
+        board.make_move(board.encode(lan)?)?;
 
         assert_eq!(board[Coordinate::F4], None);
         assert_eq!(
@@ -4776,7 +5984,7 @@ mod tests {
         let mut board = Board::from(Placement("4k3/8/8/8/8/8/8/4K2R".into()));
         let lan = Lan::try_from("e1g1")?;
 
-        board.make_move(lan)?;
+        board.make_move(board.encode(lan)?)?;
 
         assert_eq!(board[Coordinate::E1], None);
         assert_eq!(
@@ -4793,7 +6001,7 @@ mod tests {
         let mut board = Board::from(Placement("r3k3/8/8/8/8/8/8/4K3".into()));
         let lan = Lan::try_from("e8c8")?;
 
-        board.make_move(lan)?;
+        board.make_move(board.encode(lan)?)?;
 
         assert_eq!(board[Coordinate::E8], None);
         assert_eq!(
@@ -4815,17 +6023,12 @@ mod tests {
         let mut board = Board::default();
         let lan = Lan::try_from("e2e4")?;
 
-        let initial = board.clone();
-        let undoer = board.make_move(lan)?;
+        let initial = board;
+        let mv = board.encode(lan)?;
+        let undoer = board.make_move(mv)?;
 
-        assert_eq!(
-            undoer,
-            MoveUndoer {
-                lan,
-                previous: None,
-                modifer: None
-            }
-        );
+        assert_eq!(mv.flag(), MoveFlag::DoublePush);
+        assert_eq!(undoer, MoveUndoer { mv, previous: None });
 
         board.unmake_move(undoer);
 
@@ -4835,17 +6038,12 @@ mod tests {
         let mut board = Board::from(Placement("8/2k1PK2/8/8/8/8/8/8".into()));
         let lan = Lan::try_from("e7e8q")?;
 
-        let initial = board.clone();
-        let undoer = board.make_move(lan)?;
+        let initial = board;
+        let mv = board.encode(lan)?;
+        let undoer = board.make_move(mv)?;
 
-        assert_eq!(
-            undoer,
-            MoveUndoer {
-                lan,
-                previous: None,
-                modifer: Some(MoveModifier::Promotion)
-            }
-        );
+        assert_eq!(mv.flag(), MoveFlag::Promotion(PieceKind::Queen));
+        assert_eq!(undoer, MoveUndoer { mv, previous: None });
 
         board.unmake_move(undoer);
 
@@ -4855,17 +6053,12 @@ mod tests {
         let mut board = Board::from(Placement("4k3/8/8/8/4Pp2/8/8/4K3".into()));
         let lan = Lan::try_from("f4e3")?;
 
-        let initial = board.clone();
-        let undoer = board.make_move(lan)?;
+        let initial = board;
+        let mv = board.encode(lan)?;
+        let undoer = board.make_move(mv)?;
 
-        assert_eq!(
-            undoer,
-            MoveUndoer {
-                lan,
-                previous: None,
-                modifer: Some(MoveModifier::EnPassant)
-            }
-        );
+        assert_eq!(mv.flag(), MoveFlag::EnPassant);
+        assert_eq!(undoer, MoveUndoer { mv, previous: None });
 
         board.unmake_move(undoer);
 
@@ -4875,17 +6068,12 @@ mod tests {
         let mut board = Board::from(Placement("4k3/8/8/8/8/8/8/4K2R".into()));
         let lan = Lan::try_from("e1g1")?;
 
-        let initial = board.clone();
-        let undoer = board.make_move(lan)?;
+        let initial = board;
+        let mv = board.encode(lan)?;
+        let undoer = board.make_move(mv)?;
 
-        assert_eq!(
-            undoer,
-            MoveUndoer {
-                lan,
-                previous: None,
-                modifer: Some(MoveModifier::Castle)
-            }
-        );
+        assert_eq!(mv.flag(), MoveFlag::KingCastle);
+        assert_eq!(undoer, MoveUndoer { mv, previous: None });
 
         board.unmake_move(undoer);
 
@@ -4895,17 +6083,12 @@ mod tests {
         let mut board = Board::from(Placement("r3k3/8/8/8/8/8/8/4K3".into()));
         let lan = Lan::try_from("e8c8")?;
 
-        let initial = board.clone();
-        let undoer = board.make_move(lan)?;
+        let initial = board;
+        let mv = board.encode(lan)?;
+        let undoer = board.make_move(mv)?;
 
-        assert_eq!(
-            undoer,
-            MoveUndoer {
-                lan,
-                previous: None,
-                modifer: Some(MoveModifier::Castle)
-            }
-        );
+        assert_eq!(mv.flag(), MoveFlag::QueenCastle);
+        assert_eq!(undoer, MoveUndoer { mv, previous: None });
 
         board.unmake_move(undoer);
 
@@ -4918,7 +6101,7 @@ mod tests {
     fn test_placement_from_board() -> Result<(), ChessError> {
         let mut board = Board::default();
 
-        board.make_move(Lan::try_from("e2e4")?)?;
+        board.make_move(board.encode(Lan::try_from("e2e4")?)?)?;
 
         let placement = Placement::from(board);
         assert_eq!(
@@ -4928,10 +6111,10 @@ mod tests {
 
         let mut board = Board::default();
 
-        board.make_move(Lan::try_from("e2e4")?)?;
-        board.make_move(Lan::try_from("c7c5")?)?;
-        board.make_move(Lan::try_from("g1f3")?)?;
-        board.make_move(Lan::try_from("d7d6")?)?;
+        board.make_move(board.encode(Lan::try_from("e2e4")?)?)?;
+        board.make_move(board.encode(Lan::try_from("c7c5")?)?)?;
+        board.make_move(board.encode(Lan::try_from("g1f3")?)?)?;
+        board.make_move(board.encode(Lan::try_from("d7d6")?)?)?;
 
         let placement = Placement::from(board);
         assert_eq!(
@@ -4947,7 +6130,7 @@ mod tests {
         let assert_make_move = |starting_fen: &str, lan: &str, expected_fen: &str| {
             let mut state = State::from(Fen::try_from(starting_fen)?);
 
-            state.make_move(Lan::try_from(lan)?)?;
+            state.make_move(state.board.encode(Lan::try_from(lan)?)?)?;
 
             let expected = State::from(Fen::try_from(expected_fen)?);
 
@@ -5012,9 +6195,9 @@ mod tests {
     fn test_state_unmake_move() -> Result<(), ChessError> {
         let assert_make_unmake_move = |fen: &str, lan: &str| {
             let mut state = State::from(Fen::try_from(fen)?);
-            let initial = state.clone();
+            let initial = state;
 
-            let undoer = state.make_move(Lan::try_from(lan)?)?;
+            let undoer = state.make_move(state.board.encode(Lan::try_from(lan)?)?)?;
             state.unmake_move(undoer);
 
             assert_eq!(state, initial);
@@ -5034,32 +6217,373 @@ mod tests {
             "e2e4",
         )?;
 
-        // Taking en passant results in check.
-        assert_make_unmake_move("8/8/8/8/1k3p1R/8/4P3/4K3 w - - 0 1", "e2e4")?;
+        // Taking en passant results in check.
+        assert_make_unmake_move("8/8/8/8/1k3p1R/8/4P3/4K3 w - - 0 1", "e2e4")?;
+
+        // Castle kingside.
+        assert_make_unmake_move(
+            "r1bqkbnr/pp1npppp/3p4/1Bp5/4P3/5N2/PPPP1PPP/RNBQK2R w KQkq - 2 4",
+            "e1g1",
+        )?;
+
+        // The kingside rook moves; the king can no longer castle king side.
+        assert_make_unmake_move(
+            "r1bqkbnr/pp1npppp/3p4/1Bp5/4P3/5N2/PPPP1PPP/RNBQK2R w KQkq - 2 4",
+            "h1f1",
+        )?;
+
+        // The kingside rook is captured; the king can no longer castle king side.
+        assert_make_unmake_move(
+            "rnbqkb1r/pppppppp/8/8/8/6n1/PPPPPPPP/RNBQKBNR b KQkq - 7 4",
+            "g3h1",
+        )?;
+
+        // Promote a pawn to a queen.
+        assert_make_unmake_move(
+            "rnbqkbnr/ppppppPp/8/8/8/8/PPPPPPP1/RNBQKBNR w KQkq - 1 5",
+            "g7h8q",
+        )?;
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_state_null_move() -> Result<(), ChessError> {
+        let mut state = State::from(Fen::try_from(
+            "rnbqkbnr/ppp1pppp/8/8/3pP3/8/PPPP1PPP/RNBQKBNR b KQkq e3 0 3",
+        )?);
+        let initial = state;
+
+        let undoer = state.make_null_move();
+
+        assert_eq!(
+            String::from(state),
+            "rnbqkbnr/ppp1pppp/8/8/3pP3/8/PPPP1PPP/RNBQKBNR w KQkq - 0 4"
+        );
+        assert_eq!(state.hash, state.zobrist());
+
+        state.unmake_null_move(undoer);
+
+        assert_eq!(state, initial);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_state_see() -> Result<(), ChessError> {
+        let see = |fen: &str, lan: &str| State::from(Fen::try_from(fen)?).see(Lan::try_from(lan)?);
+
+        // The pawn is not defended.
+        assert_eq!(
+            see("1k1r4/1pp4p/p7/4p3/8/P5P1/1PP4P/2K1R3 w - - 0 1", "e1e5")?,
+            100
+        );
+
+        // The pawn is defended by another pawn.
+        assert_eq!(see("4k3/8/3p4/4p3/8/8/4Q3/4K3 w - - 0 1", "e2e5")?, -800);
+
+        // A pawn can happily take a defended knight.
+        assert_eq!(see("4k3/8/3p4/4n3/3P4/8/8/4K3 w - - 0 1", "d4e5")?, 200);
+
+        // The rook behind the first one recaptures through it.
+        assert_eq!(see("4r1k1/8/8/4p3/8/8/4R3/4R1K1 w - - 0 1", "e2e5")?, 100);
+        assert_eq!(see("4r1k1/8/8/4p3/8/8/4R3/6K1 w - - 0 1", "e2e5")?, -400);
+
+        // The king cannot recapture a piece that is still defended.
+        assert_eq!(see("8/8/4k3/4n3/8/8/4R3/4R1K1 w - - 0 1", "e2e5")?, 300);
+        assert_eq!(see("8/8/4k3/4n3/8/8/4R3/6K1 w - - 0 1", "e2e5")?, -200);
+
+        // Capturing en passant wins the pawn that is not on the target.
+        assert_eq!(see("4k3/8/8/2pP4/8/8/8/4K3 w - c6 0 1", "d5c6")?, 100);
+
+        // Promoting while capturing also wins the difference between a queen and a pawn.
+        assert_eq!(see("1n2k3/P7/8/8/8/8/8/4K3 w - - 0 1", "a7b8q")?, 1100);
+
+        assert!(see("4k3/8/8/8/8/8/8/4K3 w - - 0 1", "e2e4").is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_state_hash() -> Result<(), ChessError> {
+        // The incremental hash should always match a hash computed from scratch.
+        fn walk(state: &mut State, depth: u8) {
+            assert_eq!(state.hash, state.zobrist());
+
+            if depth == 0 {
+                return;
+            }
+
+            let analysis = state.analyze(state.side_to_move, MoveFilter::All);
+
+            for &lan in analysis.moves.iter() {
+                let initial = state.hash;
+                let undoer = state
+                    .make_move(lan)
+                    .expect("The given move should always be valid.");
+
+                assert_ne!(state.hash, initial);
+
+                walk(state, depth - 1);
+
+                state.unmake_move(undoer);
+
+                assert_eq!(state.hash, initial);
+            }
+        }
+
+        walk(
+            &mut State::from(Fen::try_from(
+                "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+            )?),
+            3,
+        );
+
+        // The same position reached through a different move order should share a hash.
+        let mut a = State::default();
+        let mut b = State::default();
+
+        Engine::make_sequence(
+            &mut a,
+            &[
+                Lan::try_from("g1f3")?,
+                Lan::try_from("g8f6")?,
+                Lan::try_from("b1c3")?,
+            ],
+        )?;
+        Engine::make_sequence(
+            &mut b,
+            &[
+                Lan::try_from("b1c3")?,
+                Lan::try_from("g8f6")?,
+                Lan::try_from("g1f3")?,
+            ],
+        )?;
+
+        assert_eq!(a.hash, b.hash);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_principal_variation() {
+        let mut pv = PrincipalVariation::new();
+
+        let e4 = Move::new(Coordinate::E2, Coordinate::E4, MoveFlag::DoublePush);
+        let e5 = Move::new(Coordinate::E7, Coordinate::E5, MoveFlag::DoublePush);
+        let nf3 = Move::new(Coordinate::G1, Coordinate::F3, MoveFlag::Quiet);
+
+        // Every node clears its line when it is entered, including the leaves.
+        for ply in 0..=3 {
+            pv.clear(ply);
+        }
+
+        pv.update(2, nf3);
+        pv.update(1, e5);
+        pv.update(0, e4);
+
+        assert_eq!(pv.line(), &[e4, e5, nf3]);
+
+        // A better move that has not been searched any deeper replaces the whole line.
+        pv.clear(1);
+        pv.update(0, nf3);
+
+        assert_eq!(pv.line(), &[nf3]);
+    }
+
+    #[test]
+    fn test_late_move_reductions() {
+        let table = late_move_reductions();
+
+        // Neither the first nor the second move is ever reduced.
+        assert!(table
+            .iter()
+            .all(|reductions| reductions[0] == 0 && reductions[1] == 0));
+
+        // Reductions never shrink as the depth or the move index grows.
+        for depth in 1..table.len() {
+            for index in 1..MAX_MOVES {
+                assert!(table[depth][index] >= table[depth - 1][index]);
+                assert!(table[depth][index] >= table[depth][index - 1]);
+            }
+        }
+    }
+
+    #[test]
+    fn test_late_moves_search() -> Result<(), ChessError> {
+        // Searches the State to a fixed depth, and returns its score, the amount of nodes that were
+        // searched, and the best move.
+        let search = |fen: &str, depth: u8, prune_late_moves: bool| {
+            let mut state = State::from(Fen::try_from(fen)?);
+            let mut history = Vec::new();
+            let limits = SearchLimits::default();
+            let mut table = TranspositionTable::new(MIN_HASH_SIZE);
+            let mut quiet_history = QuietHistory::default();
+
+            let mut params = SearchParams {
+                state: &mut state,
+                searched: 0,
+                history: &mut history,
+                root: 0,
+                limits: &limits,
+                table: &mut table,
+                pv: PrincipalVariation::new(),
+                quiet_history: &mut quiet_history,
+                played: [None; MAX_PLY],
+                depth,
+                prune_late_moves,
+            };
+
+            let mut score = 0;
+
+            // The same way iterative deepening would, so that move ordering has something to go on.
+            for depth in 1..=depth {
+                params.depth = depth;
+                params.pv = PrincipalVariation::new();
+                score = Engine::negamax(&mut params, depth, 0, -i16::MAX, i16::MAX);
+            }
+
+            Ok::<_, ChessError>((score, params.searched, params.pv.line().first().copied()))
+        };
+
+        // Reducing and pruning late moves still finds the same mate, only faster.
+        let mate = "k7/8/2K5/8/8/8/8/7R w - - 0 1";
+        let (pruned_score, pruned_nodes, pruned_move) = search(mate, 5, true)?;
+        let (score, nodes, mv) = search(mate, 5, false)?;
+
+        assert_eq!(pruned_score, CHECKMATE_EVALUATION - 3);
+        assert_eq!(pruned_score, score);
+        assert_eq!(pruned_move, mv);
+        assert!(pruned_nodes < nodes);
+
+        // The same goes for the best move of a quiet position.
+        let kiwipete = "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1";
+        let (_, pruned_nodes, pruned_move) = search(kiwipete, 5, true)?;
+        let (_, nodes, mv) = search(kiwipete, 5, false)?;
+
+        assert_eq!(pruned_move, mv);
+        assert!(pruned_nodes < nodes);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_quiet_history() {
+        let mut quiet_history = QuietHistory::default();
+
+        let e4 = Move::new(Coordinate::E2, Coordinate::E4, MoveFlag::DoublePush);
+        let d4 = Move::new(Coordinate::D2, Coordinate::D4, MoveFlag::DoublePush);
+        let nf3 = Move::new(Coordinate::G1, Coordinate::F3, MoveFlag::Quiet);
+        let e5 = Move::new(Coordinate::E7, Coordinate::E5, MoveFlag::DoublePush);
+
+        quiet_history.update(Color::White, e4, 3, None, 4, &[d4]);
+        quiet_history.update(Color::White, nf3, 3, Some(e5), 4, &[]);
+
+        // The most recent cutoff takes the first killer slot.
+        assert_eq!(quiet_history.killers[3], [Some(nf3), Some(e4)]);
+        assert_eq!(
+            quiet_history.score(Color::White, nf3, 3, None),
+            KILLER_ORDER
+        );
+        assert_eq!(
+            quiet_history.score(Color::White, e4, 3, None),
+            KILLER_ORDER - 1
+        );
+
+        // Killers are specific to their ply, unlike countermoves and history.
+        assert_eq!(
+            quiet_history.score(Color::White, nf3, 4, Some(e5)),
+            COUNTERMOVE_ORDER
+        );
+        assert!(quiet_history.score(Color::White, e4, 4, None) > 0);
+        assert!(quiet_history.score(Color::White, d4, 4, None) < 0);
+        assert_eq!(quiet_history.score(Color::Black, e4, 4, None), 0);
+
+        // History never grows past its limit, no matter how often a move causes a cutoff.
+        for _ in 0..1000 {
+            quiet_history.update(Color::White, e4, 3, None, MAX_SEARCH_DEPTH, &[]);
+        }
+
+        assert!(quiet_history.score(Color::White, e4, 4, None) <= MAX_HISTORY);
+
+        quiet_history.clear();
+
+        assert_eq!(quiet_history.killers[3], [None, None]);
+        assert_eq!(quiet_history.score(Color::White, e4, 4, None), 0);
+    }
+
+    #[test]
+    fn test_transposition_table() -> Result<(), ChessError> {
+        let mut table = TranspositionTable::new(MIN_HASH_SIZE);
+
+        let entry = TableEntry {
+            hash: State::default().hash,
+            depth: 4,
+            bound: Bound::Exact,
+            score: 25,
+            best: Some(Move::new(
+                Coordinate::E2,
+                Coordinate::E4,
+                MoveFlag::DoublePush,
+            )),
+            generation: 0,
+        };
+
+        assert_eq!(table.probe(entry.hash), None);
+
+        table.store(entry);
+
+        assert_eq!(table.probe(entry.hash), Some(entry));
+
+        // A shallower entry that maps to the same slot should not replace a deeper one.
+        let collision = TableEntry {
+            hash: entry.hash + table.entries.len() as u64,
+            depth: 2,
+            ..entry
+        };
+
+        table.store(collision);
+
+        assert_eq!(table.probe(entry.hash), Some(entry));
+        assert_eq!(table.probe(collision.hash), None);
 
-        // Castle kingside.
-        assert_make_unmake_move(
-            "r1bqkbnr/pp1npppp/3p4/1Bp5/4P3/5N2/PPPP1PPP/RNBQK2R w KQkq - 2 4",
-            "e1g1",
-        )?;
+        // Entries from previous searches are always replaced.
+        table.age();
+        table.store(collision);
 
-        // The kingside rook moves; the king can no longer castle king side.
-        assert_make_unmake_move(
-            "r1bqkbnr/pp1npppp/3p4/1Bp5/4P3/5N2/PPPP1PPP/RNBQK2R w KQkq - 2 4",
-            "h1f1",
-        )?;
+        assert_eq!(table.probe(entry.hash), None);
+        assert_eq!(
+            table.probe(collision.hash).map(|entry| entry.depth),
+            Some(2)
+        );
 
-        // The kingside rook is captured; the king can no longer castle king side.
-        assert_make_unmake_move(
-            "rnbqkb1r/pppppppp/8/8/8/6n1/PPPPPPPP/RNBQKBNR b KQkq - 7 4",
-            "g3h1",
-        )?;
+        table.clear();
 
-        // Promote a pawn to a queen.
-        assert_make_unmake_move(
-            "rnbqkbnr/ppppppPp/8/8/8/8/PPPPPPP1/RNBQKBNR w KQkq - 1 5",
-            "g7h8q",
-        )?;
+        assert_eq!(table.probe(collision.hash), None);
+
+        // Mate scores are stored relative to the State, and restored relative to the root.
+        let mate = CHECKMATE_EVALUATION - 5;
+
+        assert_eq!(Engine::score_to_table(mate, 2), CHECKMATE_EVALUATION - 3);
+        assert_eq!(
+            Engine::score_from_table(CHECKMATE_EVALUATION - 3, 4),
+            mate - 2
+        );
+        assert_eq!(
+            Engine::score_from_table(Engine::score_to_table(-mate, 3), 3),
+            -mate
+        );
+        assert_eq!(Engine::score_to_table(250, 3), 250);
+
+        // Mates found at the very end of a line cannot be pushed past the checkmate itself.
+        assert_eq!(
+            Engine::score_to_table(CHECKMATE_EVALUATION - 1, MAX_PLY - 1),
+            CHECKMATE_EVALUATION
+        );
+        assert_eq!(
+            Engine::score_to_table(-CHECKMATE_EVALUATION, MAX_PLY - 1),
+            -CHECKMATE_EVALUATION
+        );
 
         Ok(())
     }
@@ -5068,51 +6592,58 @@ mod tests {
     fn test_state_generate_pseudo_legal_pawn_moves() -> Result<(), ChessError> {
         // Moving None should return an empty move list.
         let state = State::default();
-        let move_list = state.generate_pseudo_legal_pawn_moves(Coordinate::E1);
-        assert_eq!(move_list, vec![]);
+        let mut move_list = MoveList::new();
+        state.generate_pseudo_legal_pawn_moves(Coordinate::E1, !Bitboard::empty(), &mut move_list);
+        assert_eq!(to_lans(&move_list), vec![]);
 
         // A pawn that hasn't moved should be able to advance one or two squares.
         let state = State::default();
-        let move_list = state.generate_pseudo_legal_pawn_moves(Coordinate::E2);
+        let mut move_list = MoveList::new();
+        state.generate_pseudo_legal_pawn_moves(Coordinate::E2, !Bitboard::empty(), &mut move_list);
         assert_eq!(
-            move_list,
+            to_lans(&move_list),
             vec![Lan::try_from("e2e3")?, Lan::try_from("e2e4")?]
         );
 
         let fen = Fen::try_from("rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq - 0 1")?;
         let state = State::from(fen);
-        let move_list = state.generate_pseudo_legal_pawn_moves(Coordinate::E7);
+        let mut move_list = MoveList::new();
+        state.generate_pseudo_legal_pawn_moves(Coordinate::E7, !Bitboard::empty(), &mut move_list);
         assert_eq!(
-            move_list,
+            to_lans(&move_list),
             vec![Lan::try_from("e7e6")?, Lan::try_from("e7e5")?]
         );
 
         // A pawn that has already moved should only be able to advance one square.
         let fen = Fen::try_from("rnbqkb1r/pppppppp/5n2/8/4P3/8/PPPP1PPP/RNBQKBNR w KQkq - 1 2")?;
         let state = State::from(fen);
-        let move_list = state.generate_pseudo_legal_pawn_moves(Coordinate::E4);
-        assert_eq!(move_list, vec![Lan::try_from("e4e5")?]);
+        let mut move_list = MoveList::new();
+        state.generate_pseudo_legal_pawn_moves(Coordinate::E4, !Bitboard::empty(), &mut move_list);
+        assert_eq!(to_lans(&move_list), vec![Lan::try_from("e4e5")?]);
 
         let fen = Fen::try_from("rnbqkbnr/pppp1ppp/8/4p3/8/8/PPPPPPPP/RNBQKBNR b KQkq - 1 2")?;
         let state = State::from(fen);
-        let move_list = state.generate_pseudo_legal_pawn_moves(Coordinate::E5);
-        assert_eq!(move_list, vec![Lan::try_from("e5e4")?]);
+        let mut move_list = MoveList::new();
+        state.generate_pseudo_legal_pawn_moves(Coordinate::E5, !Bitboard::empty(), &mut move_list);
+        assert_eq!(to_lans(&move_list), vec![Lan::try_from("e5e4")?]);
 
         // Test capturing to the top left.
         let fen = Fen::try_from("r1bqkb1r/pppppppp/2n2n2/3P4/8/8/PPP1PPPP/RNBQKBNR w KQkq - 1 3")?;
         let state = State::from(fen);
-        let move_list = state.generate_pseudo_legal_pawn_moves(Coordinate::D5);
+        let mut move_list = MoveList::new();
+        state.generate_pseudo_legal_pawn_moves(Coordinate::D5, !Bitboard::empty(), &mut move_list);
         assert_eq!(
-            move_list,
+            to_lans(&move_list),
             vec![Lan::try_from("d5d6")?, Lan::try_from("d5c6")?]
         );
 
         // Test capturing to the top right.
         let fen = Fen::try_from("r1bqkb1r/pppppppp/2n2n2/4P3/8/8/PPPP1PPP/RNBQKBNR w KQkq - 1 3")?;
         let state = State::from(fen);
-        let move_list = state.generate_pseudo_legal_pawn_moves(Coordinate::E5);
+        let mut move_list = MoveList::new();
+        state.generate_pseudo_legal_pawn_moves(Coordinate::E5, !Bitboard::empty(), &mut move_list);
         assert_eq!(
-            move_list,
+            to_lans(&move_list),
             vec![Lan::try_from("e5e6")?, Lan::try_from("e5f6")?]
         );
 
@@ -5120,44 +6651,49 @@ mod tests {
         let fen =
             Fen::try_from("rnbqkb1r/pppp1ppp/5n2/4p3/2PP4/5N2/PP2PPPP/RNBQKB1R b KQkq - 0 3")?;
         let state = State::from(fen);
-        let move_list = state.generate_pseudo_legal_pawn_moves(Coordinate::E5);
+        let mut move_list = MoveList::new();
+        state.generate_pseudo_legal_pawn_moves(Coordinate::E5, !Bitboard::empty(), &mut move_list);
         assert_eq!(
-            move_list,
+            to_lans(&move_list),
             vec![Lan::try_from("e5e4")?, Lan::try_from("e5d4")?]
         );
 
         // Test capturing to the bottom right.
         let fen = Fen::try_from("rnbqkbnr/ppp1pppp/8/3p4/4P3/2N5/PPPP1PPP/R1BQKBNR b KQkq - 1 2")?;
         let state = State::from(fen);
-        let move_list = state.generate_pseudo_legal_pawn_moves(Coordinate::D5);
+        let mut move_list = MoveList::new();
+        state.generate_pseudo_legal_pawn_moves(Coordinate::D5, !Bitboard::empty(), &mut move_list);
         assert_eq!(
-            move_list,
+            to_lans(&move_list),
             vec![Lan::try_from("d5d4")?, Lan::try_from("d5e4")?]
         );
 
         // Test ability to capture en passant.
         let fen = Fen::try_from("rnbqkbnr/ppppp1pp/8/4Pp2/8/8/PPPPKPPP/RNBQ1BNR w kq f6 0 4")?;
         let state = State::from(fen);
-        let move_list = state.generate_pseudo_legal_pawn_moves(Coordinate::E5);
+        let mut move_list = MoveList::new();
+        state.generate_pseudo_legal_pawn_moves(Coordinate::E5, !Bitboard::empty(), &mut move_list);
         assert_eq!(
-            move_list,
+            to_lans(&move_list),
             vec![Lan::try_from("e5e6")?, Lan::try_from("e5f6")?]
         );
 
         let fen = Fen::try_from("rnbqkbnr/ppppp1pp/8/8/4Pp2/8/PPPP1PPP/RNBQKBNR b KQkq e3 0 3")?;
         let state = State::from(fen);
-        let move_list = state.generate_pseudo_legal_pawn_moves(Coordinate::F4);
+        let mut move_list = MoveList::new();
+        state.generate_pseudo_legal_pawn_moves(Coordinate::F4, !Bitboard::empty(), &mut move_list);
         assert_eq!(
-            move_list,
+            to_lans(&move_list),
             vec![Lan::try_from("f4f3")?, Lan::try_from("f4e3")?]
         );
 
         // Test promotion.
         let fen = Fen::try_from("rnbqk1nr/ppppppPp/8/6p1/8/8/PPPPPPP1/RNBQKBNR w KQkq - 1 5")?;
         let state = State::from(fen);
-        let move_list = state.generate_pseudo_legal_pawn_moves(Coordinate::G7);
+        let mut move_list = MoveList::new();
+        state.generate_pseudo_legal_pawn_moves(Coordinate::G7, !Bitboard::empty(), &mut move_list);
         assert_eq!(
-            move_list,
+            to_lans(&move_list),
             vec![
                 Lan::try_from("g7h8n")?,
                 Lan::try_from("g7h8b")?,
@@ -5172,27 +6708,42 @@ mod tests {
     #[test]
     fn test_state_generate_pseudo_legal_knight_moves() -> Result<(), ChessError> {
         let state = State::default();
-        let move_list = state.generate_pseudo_legal_knight_moves(Coordinate::E1);
-        assert_eq!(move_list, vec![]);
+        let mut move_list = MoveList::new();
+        state.generate_pseudo_legal_knight_moves(
+            Coordinate::E1,
+            !Bitboard::empty(),
+            &mut move_list,
+        );
+        assert_eq!(to_lans(&move_list), vec![]);
 
         let state = State::default();
-        let move_list = state.generate_pseudo_legal_knight_moves(Coordinate::G1);
+        let mut move_list = MoveList::new();
+        state.generate_pseudo_legal_knight_moves(
+            Coordinate::G1,
+            !Bitboard::empty(),
+            &mut move_list,
+        );
         assert_eq!(
-            move_list,
-            vec![Lan::try_from("g1h3")?, Lan::try_from("g1f3")?]
+            to_lans(&move_list),
+            vec![Lan::try_from("g1f3")?, Lan::try_from("g1h3")?]
         );
 
         let fen = Fen::try_from("rnbqkbnr/pppp1ppp/8/4p3/8/5N2/PPPPPPPP/RNBQKB1R w KQkq - 0 2")?;
         let state = State::from(fen);
-        let move_list = state.generate_pseudo_legal_knight_moves(Coordinate::F3);
+        let mut move_list = MoveList::new();
+        state.generate_pseudo_legal_knight_moves(
+            Coordinate::F3,
+            !Bitboard::empty(),
+            &mut move_list,
+        );
         assert_eq!(
-            move_list,
+            to_lans(&move_list),
             vec![
+                Lan::try_from("f3e5")?,
                 Lan::try_from("f3g5")?,
+                Lan::try_from("f3d4")?,
                 Lan::try_from("f3h4")?,
                 Lan::try_from("f3g1")?,
-                Lan::try_from("f3d4")?,
-                Lan::try_from("f3e5")?,
             ]
         );
 
@@ -5202,23 +6753,38 @@ mod tests {
     #[test]
     fn test_state_generate_pseudo_legal_bishop_moves() -> Result<(), ChessError> {
         let state = State::default();
-        let move_list = state.generate_pseudo_legal_bishop_moves(Coordinate::E1);
-        assert_eq!(move_list, vec![]);
+        let mut move_list = MoveList::new();
+        state.generate_pseudo_legal_bishop_moves(
+            Coordinate::E1,
+            !Bitboard::empty(),
+            &mut move_list,
+        );
+        assert_eq!(to_lans(&move_list), vec![]);
 
         let state = State::default();
-        let move_list = state.generate_pseudo_legal_bishop_moves(Coordinate::F1);
-        assert_eq!(move_list, vec![]);
+        let mut move_list = MoveList::new();
+        state.generate_pseudo_legal_bishop_moves(
+            Coordinate::F1,
+            !Bitboard::empty(),
+            &mut move_list,
+        );
+        assert_eq!(to_lans(&move_list), vec![]);
 
         let fen = Fen::try_from("r1bqkbnr/pppppppp/8/1n6/4P3/8/PPPP1PPP/RNBQKBNR w KQkq - 5 4")?;
         let state = State::from(fen);
-        let move_list = state.generate_pseudo_legal_bishop_moves(Coordinate::F1);
+        let mut move_list = MoveList::new();
+        state.generate_pseudo_legal_bishop_moves(
+            Coordinate::F1,
+            !Bitboard::empty(),
+            &mut move_list,
+        );
         assert_eq!(
-            move_list,
+            to_lans(&move_list),
             vec![
-                Lan::try_from("f1e2")?,
-                Lan::try_from("f1d3")?,
-                Lan::try_from("f1c4")?,
                 Lan::try_from("f1b5")?,
+                Lan::try_from("f1c4")?,
+                Lan::try_from("f1d3")?,
+                Lan::try_from("f1e2")?,
             ]
         );
 
@@ -5228,26 +6794,29 @@ mod tests {
     #[test]
     fn test_state_generate_pseudo_legal_rook_moves() -> Result<(), ChessError> {
         let state = State::default();
-        let move_list = state.generate_pseudo_legal_rook_moves(Coordinate::E1);
-        assert_eq!(move_list, vec![]);
+        let mut move_list = MoveList::new();
+        state.generate_pseudo_legal_rook_moves(Coordinate::E1, !Bitboard::empty(), &mut move_list);
+        assert_eq!(to_lans(&move_list), vec![]);
 
         let state = State::default();
-        let move_list = state.generate_pseudo_legal_rook_moves(Coordinate::H1);
-        assert_eq!(move_list, vec![]);
+        let mut move_list = MoveList::new();
+        state.generate_pseudo_legal_rook_moves(Coordinate::H1, !Bitboard::empty(), &mut move_list);
+        assert_eq!(to_lans(&move_list), vec![]);
 
         let fen = Fen::try_from("rnbqkb1r/pppppppp/8/8/7P/2n4R/PPPPPPP1/R1BQKBN1 w Qkq - 0 4")?;
         let state = State::from(fen);
-        let move_list = state.generate_pseudo_legal_rook_moves(Coordinate::H3);
+        let mut move_list = MoveList::new();
+        state.generate_pseudo_legal_rook_moves(Coordinate::H3, !Bitboard::empty(), &mut move_list);
         assert_eq!(
-            move_list,
+            to_lans(&move_list),
             vec![
+                Lan::try_from("h3c3")?,
+                Lan::try_from("h3d3")?,
+                Lan::try_from("h3e3")?,
+                Lan::try_from("h3f3")?,
+                Lan::try_from("h3g3")?,
                 Lan::try_from("h3h2")?,
                 Lan::try_from("h3h1")?,
-                Lan::try_from("h3g3")?,
-                Lan::try_from("h3f3")?,
-                Lan::try_from("h3e3")?,
-                Lan::try_from("h3d3")?,
-                Lan::try_from("h3c3")?,
             ]
         );
 
@@ -5257,32 +6826,35 @@ mod tests {
     #[test]
     fn test_state_generate_pseudo_legal_queen_moves() -> Result<(), ChessError> {
         let state = State::default();
-        let move_list = state.generate_pseudo_legal_queen_moves(Coordinate::E1);
-        assert_eq!(move_list, vec![]);
+        let mut move_list = MoveList::new();
+        state.generate_pseudo_legal_queen_moves(Coordinate::E1, !Bitboard::empty(), &mut move_list);
+        assert_eq!(to_lans(&move_list), vec![]);
 
         let state = State::default();
-        let move_list = state.generate_pseudo_legal_queen_moves(Coordinate::D1);
-        assert_eq!(move_list, vec![]);
+        let mut move_list = MoveList::new();
+        state.generate_pseudo_legal_queen_moves(Coordinate::D1, !Bitboard::empty(), &mut move_list);
+        assert_eq!(to_lans(&move_list), vec![]);
 
         let fen = Fen::try_from("r1bqkbnr/pppp1ppp/2n5/4p2Q/4P3/8/PPPP1PPP/RNB1KBNR w KQkq - 2 3")?;
         let state = State::from(fen);
-        let move_list = state.generate_pseudo_legal_queen_moves(Coordinate::H5);
+        let mut move_list = MoveList::new();
+        state.generate_pseudo_legal_queen_moves(Coordinate::H5, !Bitboard::empty(), &mut move_list);
         assert_eq!(
-            move_list,
+            to_lans(&move_list),
             vec![
-                Lan::try_from("h5h6")?,
+                Lan::try_from("h5f7")?,
                 Lan::try_from("h5h7")?,
-                Lan::try_from("h5h4")?,
-                Lan::try_from("h5h3")?,
+                Lan::try_from("h5g6")?,
+                Lan::try_from("h5h6")?,
+                Lan::try_from("h5e5")?,
+                Lan::try_from("h5f5")?,
+                Lan::try_from("h5g5")?,
                 Lan::try_from("h5g4")?,
+                Lan::try_from("h5h4")?,
                 Lan::try_from("h5f3")?,
+                Lan::try_from("h5h3")?,
                 Lan::try_from("h5e2")?,
                 Lan::try_from("h5d1")?,
-                Lan::try_from("h5g5")?,
-                Lan::try_from("h5f5")?,
-                Lan::try_from("h5e5")?,
-                Lan::try_from("h5g6")?,
-                Lan::try_from("h5f7")?,
             ]
         );
 
@@ -5292,25 +6864,28 @@ mod tests {
     #[test]
     fn test_state_generate_pseudo_legal_king_moves() -> Result<(), ChessError> {
         let state = State::default();
-        let move_list = state.generate_pseudo_legal_king_moves(Coordinate::E2);
-        assert_eq!(move_list, vec![]);
+        let mut move_list = MoveList::new();
+        state.generate_pseudo_legal_king_moves(Coordinate::E2, !Bitboard::empty(), &mut move_list);
+        assert_eq!(to_lans(&move_list), vec![]);
 
         let state = State::default();
-        let move_list = state.generate_pseudo_legal_king_moves(Coordinate::E1);
-        assert_eq!(move_list, vec![]);
+        let mut move_list = MoveList::new();
+        state.generate_pseudo_legal_king_moves(Coordinate::E1, !Bitboard::empty(), &mut move_list);
+        assert_eq!(to_lans(&move_list), vec![]);
 
         let fen = Fen::try_from("rnbqkb1r/pppp1ppp/8/4p3/4n3/4K3/PPPP1PPP/RNBQ1BNR w kq - 0 4")?;
         let state = State::from(fen);
-        let move_list = state.generate_pseudo_legal_king_moves(Coordinate::E3);
+        let mut move_list = MoveList::new();
+        state.generate_pseudo_legal_king_moves(Coordinate::E3, !Bitboard::empty(), &mut move_list);
         assert_eq!(
-            move_list,
+            to_lans(&move_list),
             vec![
+                Lan::try_from("e3d4")?,
                 Lan::try_from("e3e4")?,
                 Lan::try_from("e3f4")?,
+                Lan::try_from("e3d3")?,
                 Lan::try_from("e3f3")?,
                 Lan::try_from("e3e2")?,
-                Lan::try_from("e3d3")?,
-                Lan::try_from("e3d4")?,
             ]
         );
 
@@ -5319,43 +6894,27 @@ mod tests {
 
     #[test]
     fn test_state_generate_pseudo_legal_moves() -> Result<(), ChessError> {
-        let fen = Fen::try_from("rnbq1bnr/ppppkppp/8/4p3/4P3/8/PPPPKPPP/RNBQ1BNR w - - 2 3")?;
-        let state = State::from(fen);
+        let total_moves = |state: &State, color: Color| {
+            let mut moves = MoveList::new();
 
-        let moves = state.generate_pseudo_legal_moves(Color::White);
-        let total_moves = moves
-            .iter()
-            .filter_map(|entry| entry.as_ref())
-            .fold(0, |accumulator, entry| accumulator + entry.len());
+            for start in state.board.occupancy_of(color) {
+                state.generate_pseudo_legal_moves(start, !Bitboard::empty(), &mut moves);
+            }
 
-        assert_eq!(total_moves, 23);
+            moves.len()
+        };
 
-        let moves = state.generate_pseudo_legal_moves(Color::Black);
-        let total_moves = moves
-            .iter()
-            .filter_map(|entry| entry.as_ref())
-            .fold(0, |accumulator, entry| accumulator + entry.len());
+        let fen = Fen::try_from("rnbq1bnr/ppppkppp/8/4p3/4P3/8/PPPPKPPP/RNBQ1BNR w - - 2 3")?;
+        let state = State::from(fen);
 
-        assert_eq!(total_moves, 23);
+        assert_eq!(total_moves(&state, Color::White), 23);
+        assert_eq!(total_moves(&state, Color::Black), 23);
 
         let fen = Fen::try_from("rnbqkbnr/pp2pppp/3p4/2p5/4P3/5N2/PPPP1PPP/RNBQKB1R w KQkq - 0 3")?;
         let state = State::from(fen);
 
-        let moves = state.generate_pseudo_legal_moves(Color::White);
-        let total_moves = moves
-            .iter()
-            .filter_map(|entry| entry.as_ref())
-            .fold(0, |accumulator, entry| accumulator + entry.len());
-
-        assert_eq!(total_moves, 28);
-
-        let moves = state.generate_pseudo_legal_moves(Color::Black);
-        let total_moves = moves
-            .iter()
-            .filter_map(|entry| entry.as_ref())
-            .fold(0, |accumulator, entry| accumulator + entry.len());
-
-        assert_eq!(total_moves, 29);
+        assert_eq!(total_moves(&state, Color::White), 28);
+        assert_eq!(total_moves(&state, Color::Black), 29);
 
         Ok(())
     }
@@ -5367,11 +6926,11 @@ mod tests {
         bitboard.set(Coordinate::E4, true);
         bitboard.set(Coordinate::E4, true);
         bitboard.set(Coordinate::E4, true);
-        assert_eq!(bitboard.get(Coordinate::E4), true);
+        assert!(bitboard.get(Coordinate::E4));
 
         bitboard.set(Coordinate::E4, false);
         bitboard.set(Coordinate::E4, false);
-        assert_eq!(bitboard.get(Coordinate::E4), false);
+        assert!(!bitboard.get(Coordinate::E4));
 
         Ok(())
     }
@@ -5723,49 +7282,39 @@ mod tests {
     }
 
     #[test]
-    fn test_state_sanitize_pinned_pawn() -> Result<(), ChessError> {
+    fn test_state_analyze_pinned_pawn() -> Result<(), ChessError> {
         let fen = Fen::try_from("8/6k1/8/8/8/8/2KP2q1/8 w - - 0 1")?;
         let state = State::from(fen);
 
-        let mut moves = state.generate_pseudo_legal_pawn_moves(Coordinate::D2);
-
-        state.sanitize_pinned_pawn(&mut moves, Coordinate::C2, Coordinate::D2);
+        let moves = state.legal_moves_from(Coordinate::D2);
 
         assert_eq!(moves, vec![]);
 
         let fen = Fen::try_from("8/2k5/2q5/8/8/2P5/2K5/8 w - - 0 1")?;
         let state = State::from(fen);
 
-        let mut moves = state.generate_pseudo_legal_pawn_moves(Coordinate::C3);
-
-        state.sanitize_pinned_pawn(&mut moves, Coordinate::C2, Coordinate::C3);
+        let moves = state.legal_moves_from(Coordinate::C3);
 
         assert_eq!(moves, vec![Lan::try_from("c3c4")?]);
 
         let fen = Fen::try_from("8/1K6/8/3P4/8/8/6q1/7k w - - 0 1")?;
         let state = State::from(fen);
 
-        let mut moves = state.generate_pseudo_legal_pawn_moves(Coordinate::D5);
-
-        state.sanitize_pinned_pawn(&mut moves, Coordinate::B7, Coordinate::D5);
+        let moves = state.legal_moves_from(Coordinate::D5);
 
         assert_eq!(moves, vec![]);
 
         let fen = Fen::try_from("8/6k1/5q2/8/3P4/8/1K6/8 w - - 0 1")?;
         let state = State::from(fen);
 
-        let mut moves = state.generate_pseudo_legal_pawn_moves(Coordinate::D4);
-
-        state.sanitize_pinned_pawn(&mut moves, Coordinate::B2, Coordinate::D4);
+        let moves = state.legal_moves_from(Coordinate::D4);
 
         assert_eq!(moves, vec![]);
 
         let fen = Fen::try_from("8/6k1/8/4q3/3P4/8/1K6/8 w - - 0 1")?;
         let state = State::from(fen);
 
-        let mut moves = state.generate_pseudo_legal_pawn_moves(Coordinate::D4);
-
-        state.sanitize_pinned_pawn(&mut moves, Coordinate::B2, Coordinate::D4);
+        let moves = state.legal_moves_from(Coordinate::D4);
 
         assert_eq!(moves, vec![Lan::try_from("d4e5")?]);
 
@@ -5773,209 +7322,529 @@ mod tests {
     }
 
     #[test]
-    fn test_state_sanitize_pinned_bishop() -> Result<(), ChessError> {
+    fn test_state_analyze_pinned_bishop() -> Result<(), ChessError> {
         let fen = Fen::try_from("8/8/8/8/8/8/1K1B1qk1/8 w - - 0 1")?;
         let state = State::from(fen);
 
-        let mut moves = state.generate_pseudo_legal_bishop_moves(Coordinate::D2);
-
-        state.sanitize_pinned_bishop(&mut moves, Coordinate::B2, Coordinate::D2);
+        let moves = state.legal_moves_from(Coordinate::D2);
 
         assert_eq!(moves, vec![]);
 
         let fen = Fen::try_from("8/1k6/8/1q6/1B6/8/1K6/8 w - - 0 1")?;
         let state = State::from(fen);
 
-        let mut moves = state.generate_pseudo_legal_bishop_moves(Coordinate::B4);
-
-        state.sanitize_pinned_bishop(&mut moves, Coordinate::B2, Coordinate::B4);
+        let moves = state.legal_moves_from(Coordinate::B4);
 
         assert_eq!(moves, vec![]);
 
         let fen = Fen::try_from("8/6k1/8/4q3/8/2B5/1K6/8 w - - 0 1")?;
         let state = State::from(fen);
 
-        let mut moves = state.generate_pseudo_legal_bishop_moves(Coordinate::C3);
+        let moves = state.legal_moves_from(Coordinate::C3);
+
+        assert_eq!(moves, vec![Lan::try_from("c3e5")?, Lan::try_from("c3d4")?]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_state_analyze_pinned_rook() -> Result<(), ChessError> {
+        let fen = Fen::try_from("8/6k1/5q2/8/3R4/8/1K6/8 w - - 0 1")?;
+        let state = State::from(fen);
+
+        let moves = state.legal_moves_from(Coordinate::D4);
+
+        assert_eq!(moves, vec![]);
+
+        let fen = Fen::try_from("8/1k6/1q6/8/1R6/8/1K6/8 w - - 0 1")?;
+        let state = State::from(fen);
+
+        let moves = state.legal_moves_from(Coordinate::B4);
+
+        assert_eq!(
+            moves,
+            vec![
+                Lan::try_from("b4b6")?,
+                Lan::try_from("b4b5")?,
+                Lan::try_from("b4b3")?,
+            ]
+        );
+
+        let fen = Fen::try_from("8/8/8/8/8/8/1K1R1qk1/8 w - - 0 1")?;
+        let state = State::from(fen);
+
+        let moves = state.legal_moves_from(Coordinate::D2);
+
+        assert_eq!(
+            moves,
+            vec![
+                Lan::try_from("d2c2")?,
+                Lan::try_from("d2e2")?,
+                Lan::try_from("d2f2")?,
+            ]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_state_analyze_pinned_queen() -> Result<(), ChessError> {
+        let fen = Fen::try_from("8/8/8/8/8/8/1K1Q1qk1/8 w - - 0 1")?;
+        let state = State::from(fen);
+
+        let moves = state.legal_moves_from(Coordinate::D2);
+
+        assert_eq!(
+            moves,
+            vec![
+                Lan::try_from("d2c2")?,
+                Lan::try_from("d2e2")?,
+                Lan::try_from("d2f2")?,
+            ]
+        );
+
+        let fen = Fen::try_from("8/1k6/1q6/8/1Q6/8/1K6/8 w - - 0 1")?;
+        let state = State::from(fen);
+
+        let moves = state.legal_moves_from(Coordinate::B4);
+
+        assert_eq!(
+            moves,
+            vec![
+                Lan::try_from("b4b6")?,
+                Lan::try_from("b4b5")?,
+                Lan::try_from("b4b3")?,
+            ]
+        );
+
+        let fen = Fen::try_from("8/6k1/5q2/8/3Q4/8/1K6/8 w - - 0 1")?;
+        let state = State::from(fen);
+
+        let moves = state.legal_moves_from(Coordinate::D4);
+
+        assert_eq!(
+            moves,
+            vec![
+                Lan::try_from("d4f6")?,
+                Lan::try_from("d4e5")?,
+                Lan::try_from("d4c3")?,
+            ]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_state_is_fifty_move_draw() -> Result<(), ChessError> {
+        let state = State::from(Fen::try_from("8/8/4k3/8/8/3QK3/8/8 w - - 99 80")?);
+
+        assert!(!state.is_fifty_move_draw());
+
+        let state = State::from(Fen::try_from("8/8/4k3/8/8/3QK3/8/8 b - - 100 80")?);
+
+        assert!(state.is_fifty_move_draw());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_state_has_insufficient_material() -> Result<(), ChessError> {
+        let assert_insufficient_material = |fen: &str, expected: bool| {
+            let state = State::from(Fen::try_from(fen)?);
+
+            assert_eq!(state.has_insufficient_material(), expected);
+
+            Ok(()) as Result<(), ChessError>
+        };
+
+        // K vs K.
+        assert_insufficient_material("8/8/4k3/8/8/4K3/8/8 w - - 0 1", true)?;
+        // KB vs K.
+        assert_insufficient_material("8/8/4k3/8/8/4KB2/8/8 w - - 0 1", true)?;
+        // KN vs K.
+        assert_insufficient_material("8/8/4k3/8/8/4K3/8/6n1 w - - 0 1", true)?;
+        // KB vs KB with bishops on the same color.
+        assert_insufficient_material("8/8/2b1k3/8/8/4KB2/8/8 w - - 0 1", true)?;
+        // KB vs KB with bishops on opposite colors.
+        assert_insufficient_material("8/8/3bk3/8/8/4KB2/8/8 w - - 0 1", false)?;
+        // KNN vs K.
+        assert_insufficient_material("8/8/4k3/8/8/4KN2/8/6N1 w - - 0 1", false)?;
+        // KP vs K.
+        assert_insufficient_material("8/8/4k3/8/8/4K3/4P3/8 w - - 0 1", false)?;
+        // The starting position.
+        assert_insufficient_material(
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+            false,
+        )?;
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_state_legal_moves() -> Result<(), ChessError> {
+        let state = State::default();
+
+        assert_eq!(state.legal_moves().len(), 20);
+
+        let state = State::from(Fen::try_from(
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+        )?);
+
+        assert_eq!(state.legal_moves().len(), 48);
+
+        let mut moves = state.legal_moves_from(Coordinate::E1);
+
+        moves.sort_by_key(|lan| lan.end as u8);
+
+        assert_eq!(
+            moves,
+            vec![
+                Lan::try_from("e1c1")?,
+                Lan::try_from("e1d1")?,
+                Lan::try_from("e1f1")?,
+                Lan::try_from("e1g1")?,
+            ]
+        );
+
+        // The Coordinate is empty.
+        assert_eq!(state.legal_moves_from(Coordinate::E3), vec![]);
+
+        // The piece does not belong to the side to move.
+        assert_eq!(state.legal_moves_from(Coordinate::E8), vec![]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_state_san() -> Result<(), ChessError> {
+        let assert_san = |fen: &str, lan: &str, san: &str| {
+            let state = State::from(Fen::try_from(fen)?);
+            let lan = Lan::try_from(lan)?;
+
+            assert_eq!(state.san(lan)?, san);
+            assert_eq!(state.parse_san(san)?, lan);
+
+            Ok(()) as Result<(), ChessError>
+        };
+
+        let start = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1";
+
+        assert_san(start, "e2e4", "e4")?;
+        assert_san(start, "g1f3", "Nf3")?;
+
+        // Disambiguate by file.
+        assert_san(
+            "r1bqkbnr/ppp1pppp/2n5/3p4/3P4/5N2/PPP1PPPP/RNBQKB1R w KQkq - 2 3",
+            "b1d2",
+            "Nbd2",
+        )?;
+        // Disambiguate by rank.
+        assert_san("4k3/8/8/R7/8/8/8/R3K3 w - - 0 1", "a1a3", "R1a3")?;
+        // Disambiguate by file and rank.
+        assert_san("4k3/8/8/8/8/Q6Q/8/Q3K3 w - - 0 1", "a3c3", "Qa3c3")?;
+
+        // Castling.
+        assert_san(
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+            "e1g1",
+            "O-O",
+        )?;
+        assert_san(
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+            "e1c1",
+            "O-O-O",
+        )?;
+
+        // Captures, including en passant.
+        assert_san(
+            "rnbqkbnr/ppp1pppp/8/3p4/4P3/8/PPPP1PPP/RNBQKBNR w KQkq - 0 2",
+            "e4d5",
+            "exd5",
+        )?;
+        assert_san(
+            "rnbqkbnr/ppp1p1pp/8/3pPp2/8/8/PPPP1PPP/RNBQKBNR w KQkq f6 0 3",
+            "e5f6",
+            "exf6",
+        )?;
+
+        // Promotion with check.
+        assert_san("3r2k1/4P3/8/8/8/8/8/4K3 w - - 0 1", "e7d8q", "exd8=Q+")?;
+
+        // Checkmate.
+        assert_san(
+            "rnbqkbnr/pppp1ppp/8/4p3/6P1/5P2/PPPPP2P/RNBQKBNR b KQkq - 0 2",
+            "d8h4",
+            "Qh4#",
+        )?;
+
+        let state = State::from(Fen::try_from(start)?);
 
-        state.sanitize_pinned_bishop(&mut moves, Coordinate::B2, Coordinate::C3);
+        // Suffixes and alternative spellings are tolerated.
+        assert_eq!(state.parse_san("Nf3!?")?, Lan::try_from("g1f3")?);
+
+        let promotion = State::from(Fen::try_from("3r2k1/4P3/8/8/8/8/8/4K3 w - - 0 1")?);
+
+        for san in ["exd8Q+", "exd8=q", "exd8q", "exd8=Q+"] {
+            assert_eq!(promotion.parse_san(san)?, Lan::try_from("e7d8q")?);
+        }
+
+        // Output stays canonical regardless of how the move was read.
+        assert_eq!(promotion.san(promotion.parse_san("exd8q")?)?, "exd8=Q+");
+
+        let en_passant = State::from(Fen::try_from(
+            "rnbqkbnr/ppp1p1pp/8/3pPp2/8/8/PPPP1PPP/RNBQKBNR w KQkq f6 0 3",
+        )?);
+
+        for san in ["exf6e.p.", "exf6 e.p.", "exf6 e.p.!"] {
+            assert_eq!(en_passant.parse_san(san)?, Lan::try_from("e5f6")?);
+        }
 
-        assert_eq!(moves, vec![Lan::try_from("c3d4")?, Lan::try_from("c3e5")?]);
+        // Moves that are illegal or ambiguous are rejected.
+        assert!(state.parse_san("e5").is_err());
+        assert!(state.san(Lan::try_from("e2e5")?).is_err());
+        assert!(
+            State::from(Fen::try_from("4k3/8/8/R7/8/8/8/R3K3 w - - 0 1")?)
+                .parse_san("Ra3")
+                .is_err()
+        );
 
         Ok(())
     }
 
     #[test]
-    fn test_state_sanitize_pinned_rook() -> Result<(), ChessError> {
-        let fen = Fen::try_from("8/6k1/5q2/8/3R4/8/1K6/8 w - - 0 1")?;
-        let state = State::from(fen);
+    fn test_state_play() -> Result<(), ChessError> {
+        let mut state = State::default();
+        let initial = state;
 
-        let mut moves = state.generate_pseudo_legal_rook_moves(Coordinate::D4);
+        // Moving into a square that is not reachable is illegal.
+        assert!(state.play(Lan::try_from("e2e5")?).is_err());
+        assert_eq!(state, initial);
 
-        state.sanitize_pinned_rook(&mut moves, Coordinate::B2, Coordinate::D4);
+        let undo = state.play(Lan::try_from("e2e4")?)?;
 
-        assert_eq!(moves, vec![]);
+        assert_eq!(
+            state,
+            State::from(Fen::try_from(
+                "rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq - 0 1"
+            )?)
+        );
 
-        let fen = Fen::try_from("8/1k6/1q6/8/1R6/8/1K6/8 w - - 0 1")?;
-        let state = State::from(fen);
+        // It is no longer white's turn.
+        assert!(state.play(Lan::try_from("d2d4")?).is_err());
 
-        let mut moves = state.generate_pseudo_legal_rook_moves(Coordinate::B4);
+        state.undo(undo);
 
-        state.sanitize_pinned_rook(&mut moves, Coordinate::B2, Coordinate::B4);
+        assert_eq!(state, initial);
 
-        assert_eq!(
-            moves,
-            vec![
-                Lan::try_from("b4b5")?,
-                Lan::try_from("b4b6")?,
-                Lan::try_from("b4b3")?,
-            ]
-        );
+        // Castling through check is illegal.
+        let mut state = State::from(Fen::try_from("r3k2r/8/8/8/8/5r2/8/R3K2R w KQkq - 0 1")?);
 
-        let fen = Fen::try_from("8/8/8/8/8/8/1K1R1qk1/8 w - - 0 1")?;
-        let state = State::from(fen);
+        assert!(state.play(Lan::try_from("e1g1")?).is_err());
+
+        // Moves that no piece could ever make are rejected instead of corrupting the State.
+        assert!(state.play_unchecked(Lan::try_from("e1e3")?).is_ok());
+        assert!(state.play_unchecked(Lan::try_from("e3g3")?).is_err());
 
-        let mut moves = state.generate_pseudo_legal_rook_moves(Coordinate::D2);
+        let mut state = State::from(Fen::try_from("r3k2r/8/8/8/8/5r2/8/R3K2R w KQkq - 0 1")?);
 
-        state.sanitize_pinned_rook(&mut moves, Coordinate::B2, Coordinate::D2);
+        let undo = state.play_unchecked(Lan::try_from("a1a8")?)?;
 
         assert_eq!(
-            moves,
-            vec![
-                Lan::try_from("d2e2")?,
-                Lan::try_from("d2f2")?,
-                Lan::try_from("d2c2")?,
-            ]
+            state,
+            State::from(Fen::try_from("R3k2r/8/8/8/8/5r2/8/4K2R b Kk - 0 1")?)
         );
 
-        Ok(())
-    }
-
-    #[test]
-    fn test_state_sanitize_pinned_queen() -> Result<(), ChessError> {
-        let fen = Fen::try_from("8/8/8/8/8/8/1K1Q1qk1/8 w - - 0 1")?;
-        let state = State::from(fen);
-
-        let mut moves = state.generate_pseudo_legal_queen_moves(Coordinate::D2);
-
-        state.sanitize_pinned_queen(&mut moves, Coordinate::B2, Coordinate::D2);
+        state.undo(undo);
 
         assert_eq!(
-            moves,
-            vec![
-                Lan::try_from("d2e2")?,
-                Lan::try_from("d2f2")?,
-                Lan::try_from("d2c2")?,
-            ]
+            state,
+            State::from(Fen::try_from("r3k2r/8/8/8/8/5r2/8/R3K2R w KQkq - 0 1")?)
         );
 
-        let fen = Fen::try_from("8/1k6/1q6/8/1Q6/8/1K6/8 w - - 0 1")?;
-        let state = State::from(fen);
+        Ok(())
+    }
 
-        let mut moves = state.generate_pseudo_legal_queen_moves(Coordinate::B4);
+    #[test]
+    fn test_state_outcome() -> Result<(), ChessError> {
+        let assert_outcome = |fen: &str, expected: Outcome| {
+            let state = State::from(Fen::try_from(fen)?);
 
-        state.sanitize_pinned_queen(&mut moves, Coordinate::B2, Coordinate::B4);
+            assert_eq!(state.outcome(&[]), expected);
 
-        assert_eq!(
-            moves,
-            vec![
-                Lan::try_from("b4b5")?,
-                Lan::try_from("b4b6")?,
-                Lan::try_from("b4b3")?,
-            ]
-        );
+            Ok(()) as Result<(), ChessError>
+        };
 
-        let fen = Fen::try_from("8/6k1/5q2/8/3Q4/8/1K6/8 w - - 0 1")?;
-        let state = State::from(fen);
+        assert_outcome(
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+            Outcome::Ongoing,
+        )?;
+        assert_outcome(
+            "rnb1kbnr/pppp1ppp/8/4p3/6Pq/5P2/PPPPP2P/RNBQKBNR w KQkq - 1 3",
+            Outcome::Checkmate(Color::Black),
+        )?;
+        assert_outcome("7k/5Q2/6K1/8/8/8/8/8 b - - 0 1", Outcome::Stalemate)?;
+        assert_outcome(
+            "8/8/4k3/8/8/4KB2/8/8 w - - 0 1",
+            Outcome::InsufficientMaterial,
+        )?;
+        assert_outcome("8/8/4k3/8/8/3QK3/8/8 b - - 100 80", Outcome::FiftyMove)?;
 
-        let mut moves = state.generate_pseudo_legal_queen_moves(Coordinate::D4);
+        // Checkmate takes precedence over the fifty-move rule.
+        assert_outcome(
+            "7k/6Q1/6K1/8/8/8/8/8 b - - 100 80",
+            Outcome::Checkmate(Color::White),
+        )?;
 
-        state.sanitize_pinned_queen(&mut moves, Coordinate::B2, Coordinate::D4);
+        let mut state = State::default();
+        let sequence = "g1f3 g8f6 f3g1 f6g8 g1f3 g8f6 f3g1 f6g8"
+            .split_whitespace()
+            .map(Lan::try_from)
+            .collect::<Result<Vec<Lan>, ChessError>>()?;
 
-        assert_eq!(
-            moves,
-            vec![
-                Lan::try_from("d4e5")?,
-                Lan::try_from("d4f6")?,
-                Lan::try_from("d4c3")?,
-            ]
-        );
+        let history = Engine::make_sequence(&mut state, &sequence)?;
+
+        assert_eq!(state.outcome(&history[..4]), Outcome::Ongoing);
+        assert_eq!(state.outcome(&history), Outcome::Repetition);
 
         Ok(())
     }
 
     #[test]
     fn test_state_analyze() -> Result<(), ChessError> {
-        let count_moves = |analysis: Analysis| {
-            analysis
-                .moves
-                .iter()
-                .filter_map(|entry| entry.as_ref())
-                .fold(0, |accumulator, entry| accumulator + entry.len())
-        };
-
         let fen = Fen::default();
         let state = State::from(fen);
 
-        let analysis = state.analyze(Color::White);
+        let analysis = state.analyze(Color::White, MoveFilter::All);
 
         assert_eq!(analysis.king_safety, KingSafety::Safe);
-        assert_eq!(count_moves(analysis), 20);
+        assert_eq!(analysis.moves.len(), 20);
 
         let fen = Fen::try_from("r2qnrk1/3nbppp/3pb3/5PP1/p2NP3/4B3/PPpQ3P/1K1R1B1R w - - 0 19")?;
         let state = State::from(fen);
 
-        let analysis = state.analyze(Color::White);
+        let analysis = state.analyze(Color::White, MoveFilter::All);
 
         assert_eq!(analysis.king_safety, KingSafety::Check);
-        assert_eq!(count_moves(analysis), 5);
+        assert_eq!(analysis.moves.len(), 5);
 
         let fen = Fen::try_from("2r4k/4bppp/3p4/4nPP1/1n1Bq2P/1p5R/1Q1RB3/2K5 w - - 2 35")?;
         let state = State::from(fen);
 
-        let analysis = state.analyze(Color::White);
+        let analysis = state.analyze(Color::White, MoveFilter::All);
 
         assert_eq!(analysis.king_safety, KingSafety::Check);
-        assert_eq!(count_moves(analysis), 8);
+        assert_eq!(analysis.moves.len(), 8);
 
         let fen = Fen::try_from("8/8/8/3k3r/2Pp4/8/1K6/8 b - c3 0 1")?;
         let state = State::from(fen);
 
-        let analysis = state.analyze(Color::Black);
+        let analysis = state.analyze(Color::Black, MoveFilter::All);
 
         assert_eq!(analysis.king_safety, KingSafety::Check);
-        assert_eq!(count_moves(analysis), 8);
+        assert_eq!(analysis.moves.len(), 8);
 
         let fen = Fen::try_from("r1bqkbnr/pppp1Qpp/8/4p3/2BnP3/8/PPPP1PPP/RNB1K1NR b KQkq - 0 4")?;
         let state = State::from(fen);
 
-        let analysis = state.analyze(Color::Black);
+        let analysis = state.analyze(Color::Black, MoveFilter::All);
 
         assert_eq!(analysis.king_safety, KingSafety::Checkmate);
-        assert_eq!(count_moves(analysis), 0);
+        assert_eq!(analysis.moves.len(), 0);
 
         let fen = Fen::try_from("k7/2Q5/1K6/8/8/8/8/8 b - - 0 1")?;
         let state = State::from(fen);
 
-        let analysis = state.analyze(Color::Black);
+        let analysis = state.analyze(Color::Black, MoveFilter::All);
 
         assert_eq!(analysis.king_safety, KingSafety::Stalemate);
-        assert_eq!(count_moves(analysis), 0);
+        assert_eq!(analysis.moves.len(), 0);
 
         let fen = Fen::try_from("8/8/1R6/pP1pBk1p/P2P3K/5P2/8/8 b - - 0 69")?;
         let state = State::from(fen);
 
-        let analysis = state.analyze(Color::Black);
+        let analysis = state.analyze(Color::Black, MoveFilter::All);
 
         assert_eq!(analysis.king_safety, KingSafety::Stalemate);
-        assert_eq!(count_moves(analysis), 0);
+        assert_eq!(analysis.moves.len(), 0);
 
         let fen = Fen::try_from("rnbqk1nr/pppp1ppp/4p3/8/1b6/3P4/PPPKPPPP/RNBQ1BNR w kq - 2 3")?;
         let state = State::from(fen);
 
-        let analysis = state.analyze(Color::White);
+        let analysis = state.analyze(Color::White, MoveFilter::All);
+
+        assert_eq!(analysis.king_safety, KingSafety::Check);
+        assert_eq!(analysis.moves.len(), 3);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_state_analyze_filters() -> Result<(), ChessError> {
+        let positions = [
+            // Captures, castling, and en passant.
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+            "r3k2r/p2pqpb1/bn2pnp1/2pPN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq c6 0 2",
+            // Promoting by advancing and by capturing.
+            "n1n5/PPPk4/8/8/8/8/4Kppp/5N1N b - - 0 1",
+            // Check.
+            "rnbqk1nr/pppp1ppp/4p3/8/1b6/3P4/PPPKPPPP/RNBQ1BNR w kq - 2 3",
+        ];
+
+        for fen in positions {
+            let state = State::from(Fen::try_from(fen)?);
+            let color = state.side_to_move;
+
+            let all = state.analyze(color, MoveFilter::All).moves;
+            let captures = state.analyze(color, MoveFilter::Captures).moves;
+            let quiets = state.analyze(color, MoveFilter::Quiets).moves;
+
+            let is_capture = |mv: &Move| {
+                state.board[mv.end()].is_some()
+                    || (state.board[mv.start()] == Some(Piece(color, PieceKind::Pawn))
+                        && Some(mv.end()) == state.en_passant_target)
+            };
+
+            assert!(captures.iter().all(|mv| is_capture(mv) && mv.is_capture()));
+            assert!(!quiets.iter().any(|mv| is_capture(mv) || mv.is_capture()));
+            assert_eq!(captures.len() + quiets.len(), all.len());
+            assert!(captures
+                .iter()
+                .chain(quiets.iter())
+                .all(|mv| all.contains(mv)));
+        }
+
+        // Only every move can tell whether or not there is a way out of check.
+        let fen = Fen::try_from("r1bqkbnr/pppp1Qpp/8/4p3/2BnP3/8/PPPP1PPP/RNB1K1NR b KQkq - 0 4")?;
+        let state = State::from(fen);
+
+        let analysis = state.analyze(Color::Black, MoveFilter::Captures);
 
         assert_eq!(analysis.king_safety, KingSafety::Check);
-        assert_eq!(count_moves(analysis), 3);
+        assert_eq!(analysis.moves.len(), 0);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_engine_perft_parallel() -> Result<(), ChessError> {
+        let state = State::from(Fen::try_from(
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+        )?);
+
+        for depth in 0..=3 {
+            let expected = Engine::perft(&mut state.clone(), depth);
+
+            assert_eq!(Engine::perft_parallel(&state, depth, 1), expected);
+            assert_eq!(Engine::perft_parallel(&state, depth, 4), expected);
+        }
+
+        // Subtrees that end in checkmate before the split do not count.
+        let state = State::from(Fen::try_from("7k/8/6QK/8/8/8/8/8 w - - 0 1")?);
+
+        assert_eq!(
+            Engine::perft_parallel(&state, 3, 4),
+            Engine::perft(&mut state.clone(), 3)
+        );
 
         Ok(())
     }
@@ -6035,6 +7904,45 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_engine_is_repetition() -> Result<(), ChessError> {
+        let play = |sequence: &str| {
+            let mut state = State::default();
+            let sequence = sequence
+                .split_whitespace()
+                .map(Lan::try_from)
+                .collect::<Result<Vec<Lan>, ChessError>>()?;
+
+            let history = Engine::make_sequence(&mut state, &sequence)?;
+
+            Ok((state, history)) as Result<(State, Vec<u64>), ChessError>
+        };
+
+        let (state, history) = play("g1f3 g8f6 f3g1 f6g8")?;
+
+        assert_eq!(state.hash, State::default().hash);
+        assert_eq!(history.len(), 4);
+
+        // The starting position has only occurred twice in the game.
+        assert!(!Engine::is_repetition(&state, &history, history.len()));
+
+        // Repeating a position within the search is enough to call it a draw.
+        assert!(Engine::is_repetition(&state, &history, 0));
+
+        let (state, history) = play("g1f3 g8f6 f3g1 f6g8 g1f3 g8f6 f3g1 f6g8")?;
+
+        // The starting position has now occurred three times.
+        assert!(Engine::is_repetition(&state, &history, history.len()));
+
+        // A pawn move means none of the previous positions can ever be reached again.
+        let (state, history) = play("g1f3 g8f6 f3g1 f6g8 e2e4 e7e5 g1f3 g8f6 f3g1 f6g8")?;
+
+        assert!(Engine::is_repetition(&state, &history, 0));
+        assert!(!Engine::is_repetition(&state, &history, history.len()));
+
+        Ok(())
+    }
+
     #[test]
     fn test_engine_analyze() -> Result<(), ChessError> {
         // We cannot reliably test most of InfoStatistics' properties, but we can test whether or
@@ -6044,20 +7952,355 @@ mod tests {
             "6k1/pp3r2/6rp/3QN3/5p2/2P1p2R/PPq3PP/4R1K1 b - - 0 1",
         )?);
 
-        let (evaluation, info) = Engine::analyze(&mut state, 3, None);
+        let (score, info) = Engine::analyze(
+            &mut state,
+            3,
+            &mut Vec::new(),
+            &Default::default(),
+            &mut TranspositionTable::new(MIN_HASH_SIZE),
+            &mut QuietHistory::default(),
+        )
+        .expect("The search should not have been interrupted.");
 
-        assert_eq!(evaluation, Evaluation::Winner(Color::Black));
+        assert_eq!(score, CHECKMATE_EVALUATION - 3);
         assert_eq!(info.score, Some(Score::Mate(2)));
 
         let mut state = State::from(Fen::try_from(
             "6k1/pp3r2/6rp/3QN3/5p2/2P1p2R/PP3qPP/4R1K1 w - - 1 2",
         )?);
 
-        let (evaluation, info) = Engine::analyze(&mut state, 3, None);
+        let (score, info) = Engine::analyze(
+            &mut state,
+            3,
+            &mut Vec::new(),
+            &Default::default(),
+            &mut TranspositionTable::new(MIN_HASH_SIZE),
+            &mut QuietHistory::default(),
+        )
+        .expect("The search should not have been interrupted.");
 
-        assert_eq!(evaluation, Evaluation::Winner(Color::Black));
+        assert_eq!(score, -CHECKMATE_EVALUATION + 2);
         assert_eq!(info.score, Some(Score::Mate(-1)));
 
         Ok(())
     }
+
+    #[test]
+    fn test_engine_ply_cap() -> Result<(), ChessError> {
+        // Searches a State as if it was reached right before the end of a very long line.
+        let search = |fen: &str, depth: u8, ply: usize| -> Result<i16, ChessError> {
+            let mut state = State::from(Fen::try_from(fen)?);
+            let mut history = Vec::new();
+            let limits = SearchLimits::default();
+            let mut table = TranspositionTable::new(MIN_HASH_SIZE);
+            let mut quiet_history = QuietHistory::default();
+
+            let mut params = SearchParams {
+                state: &mut state,
+                searched: 0,
+                history: &mut history,
+                root: 0,
+                limits: &limits,
+                table: &mut table,
+                pv: PrincipalVariation::new(),
+                quiet_history: &mut quiet_history,
+                played: [None; MAX_PLY],
+                depth,
+                prune_late_moves: true,
+            };
+
+            Ok(Engine::negamax(
+                &mut params,
+                depth,
+                ply,
+                -i16::MAX,
+                i16::MAX,
+            ))
+        };
+
+        // White is lost, but can keep checking until the ply cap is reached.
+        let perpetual = "6rk/7p/8/8/1rr5/5Q2/6PP/7K w - - 0 1";
+
+        assert_eq!(search(perpetual, 6, MAX_PLY - 6)?, 0);
+
+        // Delivering checkmate on the very last ply is still scored as a mate.
+        let mate = "6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1";
+
+        assert_eq!(
+            search(mate, 1, MAX_PLY - 2)?,
+            CHECKMATE_EVALUATION - (MAX_PLY - 1) as i16
+        );
+
+        // The whole perpetual can be searched from the root without running away.
+        let mut state = State::from(Fen::try_from(perpetual)?);
+
+        let (score, _) = Engine::analyze(
+            &mut state,
+            8,
+            &mut Vec::new(),
+            &Default::default(),
+            &mut TranspositionTable::new(MIN_HASH_SIZE),
+            &mut QuietHistory::default(),
+        )
+        .expect("The search should not have been interrupted.");
+
+        assert_eq!(score, 0);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_go_command() -> Result<(), ChessError> {
+        let parse_search = |command: &str| match Command::try_from(command)? {
+            Command::Go(GoParams::Search(search)) => Ok(search),
+            _ => Err(ChessError(ChessErrorKind::Other, "Expected a search.")),
+        };
+
+        // Every limit that was given is kept.
+        let search = parse_search("go depth 5 wtime 1000 btime 2000 movetime 300 nodes 4000")?;
+
+        assert_eq!(search.depth, Some(5));
+        assert_eq!(search.clock.and_then(|clock| clock.btime), Some(2000));
+        assert_eq!(search.movetime, Some(300));
+        assert_eq!(search.nodes, Some(4000));
+        assert!(!search.infinite);
+
+        // Unsupported subcommands are skipped.
+        let search = parse_search("go ponder wtime 1000 btime 2000")?;
+
+        assert_eq!(search.clock.and_then(|clock| clock.wtime), Some(1000));
+
+        let search = parse_search("go searchmoves e2e4 d2d4 depth 3")?;
+
+        assert_eq!(search.depth, Some(3));
+        assert!(search.clock.is_none());
+
+        // Malformed values of supported subcommands are still errors.
+        assert!(Command::try_from("go depth deep").is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_time_budget() -> Result<(), ChessError> {
+        let parse_clock = |command: &str| match Command::try_from(command)? {
+            Command::Go(GoParams::Search(GoSearch {
+                clock: Some(clock), ..
+            })) => Ok(clock),
+            _ => Err(ChessError(ChessErrorKind::Other, "Expected a clock.")),
+        };
+
+        let clock = parse_clock("go wtime 60000 btime 30000 winc 1000 binc 500")?;
+
+        assert_eq!(
+            clock,
+            Clock {
+                wtime: Some(60_000),
+                btime: Some(30_000),
+                winc: 1000,
+                binc: 500,
+                movestogo: None,
+            }
+        );
+
+        // Sudden death with an increment.
+        let budget = TimeBudget::new(clock, Color::White);
+
+        assert_eq!(
+            budget,
+            TimeBudget {
+                soft: 2748,
+                hard: 8244
+            }
+        );
+
+        let budget = TimeBudget::new(clock, Color::Black);
+
+        assert_eq!(
+            budget,
+            TimeBudget {
+                soft: 1373,
+                hard: 4119
+            }
+        );
+
+        // The last move before the next time control should not use up the entire clock.
+        let clock = parse_clock("go wtime 10000 btime 10000 movestogo 1")?;
+        let budget = TimeBudget::new(clock, Color::White);
+
+        assert_eq!(
+            budget,
+            TimeBudget {
+                soft: 4975,
+                hard: 7462
+            }
+        );
+
+        // A fallen flag (or a missing clock) still leaves a moment to think.
+        let clock = parse_clock("go wtime -20 btime 10000")?;
+        let budget = TimeBudget::new(clock, Color::White);
+
+        assert_eq!(budget, TimeBudget { soft: 10, hard: 10 });
+
+        let clock = parse_clock("go btime 10000")?;
+        let budget = TimeBudget::new(clock, Color::White);
+
+        assert_eq!(budget, TimeBudget { soft: 10, hard: 10 });
+
+        // Same goes for a fixed amount of time that the overhead would otherwise use up.
+        assert_eq!(TimeBudget::fixed(40), TimeBudget { soft: 10, hard: 10 });
+        assert_eq!(
+            TimeBudget::fixed(1000),
+            TimeBudget {
+                soft: 950,
+                hard: 950
+            }
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_pescado_stop() {
+        use std::cell::RefCell;
+        use std::rc::Rc;
+
+        let output = Rc::new(RefCell::new(Vec::new()));
+        let sink = Rc::clone(&output);
+
+        let mut engine = Pescado::new(move |message| sink.borrow_mut().push(message));
+
+        // A pending stop request interrupts the search right away, but a legal move is still
+        // suggested.
+        engine.stop_handle().stop();
+        engine.send("go depth 4");
+
+        let lines = output.take();
+
+        assert_eq!(lines.len(), 1);
+        assert!(lines[0].starts_with("bestmove "));
+
+        let lan = lines[0].split_whitespace().nth(1).unwrap_or_default();
+
+        assert!(State::default()
+            .legal_moves()
+            .iter()
+            .any(|legal| legal.to_string() == lan));
+
+        // Sending `stop` clears the request.
+        engine.send("stop");
+        engine.send("go depth 2");
+
+        let lines = output.take();
+
+        assert_eq!(lines.len(), 3);
+        assert!(lines[1].starts_with("info depth 2 "));
+    }
+
+    #[test]
+    fn test_pescado_flip() -> Result<(), ChessError> {
+        let mut engine = Pescado::new(|_| {});
+
+        engine.send("position fen 4k3/8/8/3pP3/8/8/8/4K3 w - d6 7 42");
+        engine.send("flip");
+
+        assert_eq!(
+            engine.state,
+            State::from(Fen::try_from("4k3/8/8/3pP3/8/8/8/4K3 b - - 7 42")?)
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_pescado_go_limits() {
+        use std::cell::RefCell;
+        use std::rc::Rc;
+
+        let output = Rc::new(RefCell::new(Vec::new()));
+        let sink = Rc::clone(&output);
+
+        let mut engine = Pescado::new(move |message| sink.borrow_mut().push(message));
+
+        // Only complete iterations are reported, and their nodes add up across the whole search,
+        // so they should never exceed the node limit.
+        engine.send("go nodes 2000");
+
+        let lines = output.take();
+        let searched = lines
+            .iter()
+            .filter_map(|line| {
+                line.split_whitespace()
+                    .skip_while(|&word| word != "nodes")
+                    .nth(1)
+            })
+            .map(|nodes| nodes.parse::<u128>().expect("Expected a number."))
+            .collect::<Vec<u128>>();
+
+        assert!(lines.len() >= 3);
+        assert!(searched.windows(2).all(|pair| pair[0] < pair[1]));
+        assert!(searched.iter().all(|&nodes| nodes <= 2000));
+        assert!(lines[lines.len() - 1].starts_with("bestmove "));
+
+        // Even the shortest amount of time is enough to finish the first iteration.
+        for command in ["go movetime 1", "go wtime 45 btime 45", "go nodes 1"] {
+            engine.send(command);
+
+            let lines = output.take();
+
+            assert!(lines.iter().any(|line| line.starts_with("info depth 1 ")));
+            assert!(lines[lines.len() - 1].starts_with("bestmove "));
+        }
+
+        // An infinite search only suggests a move once it is stopped.
+        engine.stop_handle().stop();
+        engine.send("go infinite");
+
+        let lines = output.take();
+
+        assert_eq!(lines.len(), 1);
+        assert!(lines[0].starts_with("bestmove "));
+    }
+
+    #[test]
+    fn test_pescado_game() -> Result<(), ChessError> {
+        use std::cell::RefCell;
+        use std::rc::Rc;
+
+        let output = Rc::new(RefCell::new(Vec::new()));
+        let sink = Rc::clone(&output);
+
+        let mut engine = Pescado::new(move |message| sink.borrow_mut().push(message));
+
+        engine.send("position startpos moves f2f3 e7e5 g2g4");
+        engine.send("go depth 2");
+
+        let lines = output.take();
+        let bestmove = lines[lines.len() - 1]
+            .split_whitespace()
+            .nth(1)
+            .expect("Expected a move to be suggested.");
+
+        assert_eq!(bestmove, "d8h4");
+
+        engine.send(&format!(
+            "position startpos moves f2f3 e7e5 g2g4 {}",
+            bestmove
+        ));
+
+        let game = engine.game();
+
+        assert_eq!(game.result, pgn::GameResult::BlackWins);
+        assert_eq!(game.tag("Result"), Some("0-1"));
+        assert_eq!(game.tag("FEN"), None);
+
+        let pgn = game.to_string();
+
+        assert!(pgn.ends_with("1. f3 e5 2. g4 Qh4# {+M1/1} 0-1\n"));
+
+        // The exported game can be read back in.
+        assert_eq!(pgn::Game::try_from(pgn.as_str())?, game);
+
+        Ok(())
+    }
 }
@@ -0,0 +1,856 @@
+//! Reading and writing games in Portable Game Notation.
+//!
+//! Learn more about PGN here: https://www.chessprogramming.org/Portable_Game_Notation
+
+use crate::{ChessError, ChessErrorKind, Color, Fen, Lan, State};
+use std::fmt::Display;
+
+/// PGN export format requires lines to be less than eighty characters long.
+const MAX_LINE_LENGTH: usize = 79;
+
+/// The tags every game is expected to have, in the order they are expected to appear.
+const SEVEN_TAG_ROSTER: [(&str, &str); 7] = [
+    ("Event", "?"),
+    ("Site", "?"),
+    ("Date", "????.??.??"),
+    ("Round", "?"),
+    ("White", "?"),
+    ("Black", "?"),
+    ("Result", "*"),
+];
+
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub enum GameResult {
+    WhiteWins,
+    BlackWins,
+    Draw,
+    /// The game is still in progress, or its result is unknown.
+    Unknown,
+}
+
+impl TryFrom<&str> for GameResult {
+    type Error = ChessError;
+
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        match value {
+            "1-0" => Ok(GameResult::WhiteWins),
+            "0-1" => Ok(GameResult::BlackWins),
+            "1/2-1/2" => Ok(GameResult::Draw),
+            "*" => Ok(GameResult::Unknown),
+            _ => Err(ChessError(
+                ChessErrorKind::InvalidString,
+                "A game result can only be one of <1-0 | 0-1 | 1/2-1/2 | *>.",
+            )),
+        }
+    }
+}
+
+impl<'a> From<GameResult> for &'a str {
+    fn from(value: GameResult) -> &'a str {
+        match value {
+            GameResult::WhiteWins => "1-0",
+            GameResult::BlackWins => "0-1",
+            GameResult::Draw => "1/2-1/2",
+            GameResult::Unknown => "*",
+        }
+    }
+}
+
+impl Display for GameResult {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", <&str>::from(*self))
+    }
+}
+
+/// A move within a game, and every move that can follow it.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Node {
+    pub lan: Lan,
+    /// The move in Standard Algebraic Notation.
+    pub san: String,
+    /// Numeric Annotation Glyphs (e.g. `$1` is a good move).
+    pub nags: Vec<u8>,
+    /// A comment that appears before the move.
+    pub preceding_comment: Option<String>,
+    /// A comment that appears after the move.
+    pub comment: Option<String>,
+    /// The first child continues the current line; any others are variations.
+    pub children: Vec<Node>,
+}
+
+impl Node {
+    /// Creates a Node for the given legal move.
+    pub fn new(state: &State, lan: Lan) -> Result<Self, ChessError> {
+        Ok(Node {
+            lan,
+            san: state.san(lan)?,
+            nags: Vec::new(),
+            preceding_comment: None,
+            comment: None,
+            children: Vec::new(),
+        })
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Game {
+    /// Tag pairs in the order they appear (e.g. `("Event", "F/S Return Match")`).
+    pub tags: Vec<(String, String)>,
+    /// The first Node continues the game; any others are variations.
+    pub moves: Vec<Node>,
+    pub result: GameResult,
+}
+
+impl Default for Game {
+    fn default() -> Self {
+        Game {
+            tags: SEVEN_TAG_ROSTER
+                .iter()
+                .map(|(name, value)| (String::from(*name), String::from(*value)))
+                .collect(),
+            moves: Vec::new(),
+            result: GameResult::Unknown,
+        }
+    }
+}
+
+impl Game {
+    /// Returns the value of the first tag with the given name.
+    pub fn tag(&self, name: &str) -> Option<&str> {
+        self.tags
+            .iter()
+            .find(|(key, _)| key == name)
+            .map(|(_, value)| value.as_str())
+    }
+
+    /// Sets the value of the given tag, adding it if it does not exist yet.
+    pub fn set_tag(&mut self, name: &str, value: &str) {
+        match self.tags.iter_mut().find(|(key, _)| key == name) {
+            Some(tag) => tag.1 = String::from(value),
+            None => self.tags.push((String::from(name), String::from(value))),
+        }
+    }
+
+    /// Returns the State the game started from; games can start from a custom position by
+    /// including a FEN tag.
+    pub fn initial_state(&self) -> Result<State, ChessError> {
+        match self.tag("FEN") {
+            Some(fen) => Ok(State::from(Fen::try_from(fen)?)),
+            None => Ok(State::default()),
+        }
+    }
+
+    /// Returns every move of the game, ignoring variations.
+    pub fn mainline(&self) -> Vec<Lan> {
+        let mut result = Vec::new();
+        let mut children = &self.moves;
+
+        while let Some(node) = children.first() {
+            result.push(node.lan);
+            children = &node.children;
+        }
+
+        result
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+enum Token {
+    Tag(String, String),
+    Comment(String),
+    Nag(u8),
+    OpenVariation,
+    CloseVariation,
+    /// Moves, move numbers, and game results.
+    Symbol(String),
+}
+
+fn tokenize(text: &str) -> Result<Vec<Token>, ChessError> {
+    let mut tokens = Vec::new();
+    let mut characters = text.chars().peekable();
+    let mut start_of_line = true;
+
+    while let Some(character) = characters.next() {
+        let was_start_of_line = start_of_line;
+
+        start_of_line = character == '\n';
+
+        match character {
+            _ if character.is_whitespace() => (),
+            // Lines that begin with a percent sign are escaped from the rest of the format.
+            '%' if was_start_of_line => {
+                for character in characters.by_ref() {
+                    if character == '\n' {
+                        start_of_line = true;
+                        break;
+                    }
+                }
+            }
+            '[' => {
+                let mut name = String::new();
+
+                while let Some(&character) = characters.peek() {
+                    if character.is_whitespace() || character == '"' {
+                        break;
+                    }
+
+                    name.push(character);
+                    characters.next();
+                }
+
+                while characters
+                    .next_if(|character| character.is_whitespace())
+                    .is_some()
+                {}
+
+                if characters.next() != Some('"') {
+                    return Err(ChessError(
+                        ChessErrorKind::InvalidString,
+                        "Expected the value of a tag to be surrounded by quotes.",
+                    ));
+                }
+
+                let mut value = String::new();
+
+                loop {
+                    match characters.next() {
+                        Some('\\') => match characters.next() {
+                            Some(character) => value.push(character),
+                            None => break,
+                        },
+                        Some('"') => break,
+                        Some(character) => value.push(character),
+                        None => {
+                            return Err(ChessError(
+                                ChessErrorKind::InvalidString,
+                                "The value of a tag was never closed.",
+                            ))
+                        }
+                    }
+                }
+
+                while characters
+                    .next_if(|character| character.is_whitespace())
+                    .is_some()
+                {}
+
+                if characters.next() != Some(']') {
+                    return Err(ChessError(
+                        ChessErrorKind::InvalidString,
+                        "Expected a tag to end with a closing bracket.",
+                    ));
+                }
+
+                tokens.push(Token::Tag(name, value));
+            }
+            '{' => {
+                let mut comment = String::new();
+
+                loop {
+                    match characters.next() {
+                        Some('}') => break,
+                        Some(character) => comment.push(character),
+                        None => {
+                            return Err(ChessError(
+                                ChessErrorKind::InvalidString,
+                                "A comment was never closed.",
+                            ))
+                        }
+                    }
+                }
+
+                tokens.push(Token::Comment(String::from(comment.trim())));
+            }
+            ';' => {
+                let mut comment = String::new();
+
+                for character in characters.by_ref() {
+                    if character == '\n' {
+                        start_of_line = true;
+                        break;
+                    }
+
+                    comment.push(character);
+                }
+
+                tokens.push(Token::Comment(String::from(comment.trim())));
+            }
+            '$' => {
+                let mut nag = String::new();
+
+                while let Some(character) =
+                    characters.next_if(|character| character.is_ascii_digit())
+                {
+                    nag.push(character);
+                }
+
+                let nag = nag.parse::<u8>().map_err(|_| {
+                    ChessError(
+                        ChessErrorKind::InvalidString,
+                        "Expected a number between 0 and 255 to follow $.",
+                    )
+                })?;
+
+                tokens.push(Token::Nag(nag));
+            }
+            '(' => tokens.push(Token::OpenVariation),
+            ')' => tokens.push(Token::CloseVariation),
+            _ => {
+                let mut symbol = String::from(character);
+
+                while let Some(&character) = characters.peek() {
+                    if character.is_whitespace() || "[]{}();$".contains(character) {
+                        break;
+                    }
+
+                    symbol.push(character);
+                    characters.next();
+                }
+
+                tokenize_symbol(&symbol, &mut tokens);
+            }
+        }
+    }
+
+    Ok(tokens)
+}
+
+/// Splits a symbol into its move number, move, and any suffix annotations (e.g. `12.Nf3!?`).
+fn tokenize_symbol(symbol: &str, tokens: &mut Vec<Token>) {
+    if GameResult::try_from(symbol).is_ok() {
+        tokens.push(Token::Symbol(String::from(symbol)));
+
+        return;
+    }
+
+    // Move numbers (e.g. `12.` or `12...`) are only there to help humans read the game.
+    let symbol = match symbol.find('.') {
+        Some(index) if symbol[..index].chars().all(|c| c.is_ascii_digit()) => {
+            symbol[index..].trim_start_matches('.')
+        }
+        _ => symbol,
+    };
+
+    // A separate `e.p.` only points out that the previous move captured en passant.
+    if symbol.is_empty() || symbol == "e.p." {
+        return;
+    }
+
+    let san = symbol.trim_end_matches(['!', '?']);
+    let suffix = &symbol[san.len()..];
+
+    if !san.is_empty() {
+        tokens.push(Token::Symbol(String::from(san)));
+    }
+
+    let nag = match suffix {
+        "!" => Some(1),
+        "?" => Some(2),
+        "!!" => Some(3),
+        "??" => Some(4),
+        "!?" => Some(5),
+        "?!" => Some(6),
+        _ => None,
+    };
+
+    if let Some(nag) = nag {
+        tokens.push(Token::Nag(nag));
+    }
+}
+
+/// A move as it appears in a line, before the line is folded into a tree.
+struct Entry {
+    node: Node,
+    /// Lines that could have been played instead of this move.
+    variations: Vec<Vec<Entry>>,
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    index: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.index)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.index).cloned();
+
+        self.index += 1;
+
+        token
+    }
+
+    fn parse_game(&mut self) -> Result<Game, ChessError> {
+        let mut tags = Vec::new();
+
+        while let Some(Token::Tag(_, _)) = self.peek() {
+            if let Some(Token::Tag(name, value)) = self.next() {
+                tags.push((name, value));
+            }
+        }
+
+        let mut game = Game {
+            tags,
+            moves: Vec::new(),
+            result: GameResult::Unknown,
+        };
+
+        let (line, result) = self.parse_line(game.initial_state()?, false)?;
+
+        game.moves = fold(line);
+        game.result = result.unwrap_or(GameResult::Unknown);
+
+        Ok(game)
+    }
+
+    /// Parses moves until the end of the line, and returns how the game ended if the line was the
+    /// mainline.
+    fn parse_line(
+        &mut self,
+        mut state: State,
+        variation: bool,
+    ) -> Result<(Vec<Entry>, Option<GameResult>), ChessError> {
+        let mut line: Vec<Entry> = Vec::new();
+        let mut previous = state;
+        let mut pending_comment: Option<String> = None;
+
+        loop {
+            let token = match self.peek() {
+                Some(Token::Tag(_, _)) if !variation => break,
+                Some(token) => token.clone(),
+                None => break,
+            };
+
+            self.index += 1;
+
+            match token {
+                Token::Tag(_, _) => {
+                    return Err(ChessError(
+                        ChessErrorKind::InvalidString,
+                        "Tags cannot appear within a variation.",
+                    ));
+                }
+                Token::Comment(comment) => match (line.last_mut(), &pending_comment) {
+                    (Some(entry), None) => {
+                        entry.node.comment = Some(match entry.node.comment.take() {
+                            Some(existing) => format!("{} {}", existing, comment),
+                            None => comment,
+                        });
+                    }
+                    _ => {
+                        pending_comment = Some(match pending_comment.take() {
+                            Some(existing) => format!("{} {}", existing, comment),
+                            None => comment,
+                        });
+                    }
+                },
+                Token::Nag(nag) => match line.last_mut() {
+                    Some(entry) => entry.node.nags.push(nag),
+                    None => {
+                        return Err(ChessError(
+                            ChessErrorKind::InvalidString,
+                            "A NAG must follow a move.",
+                        ));
+                    }
+                },
+                Token::OpenVariation => {
+                    let (variation, _) = self.parse_line(previous, true)?;
+
+                    match line.last_mut() {
+                        Some(entry) => entry.variations.push(variation),
+                        None => {
+                            return Err(ChessError(
+                                ChessErrorKind::InvalidString,
+                                "A variation must follow a move.",
+                            ));
+                        }
+                    }
+                }
+                Token::CloseVariation => {
+                    if variation {
+                        return Ok((line, None));
+                    }
+
+                    return Err(ChessError(
+                        ChessErrorKind::InvalidString,
+                        "A variation was closed without being opened.",
+                    ));
+                }
+                Token::Symbol(symbol) => {
+                    if let Ok(result) = GameResult::try_from(symbol.as_str()) {
+                        if variation {
+                            return Err(ChessError(
+                                ChessErrorKind::InvalidString,
+                                "A game cannot end within a variation.",
+                            ));
+                        }
+
+                        return Ok((line, Some(result)));
+                    }
+
+                    let lan = state.parse_san(&symbol)?;
+                    let mut node = Node::new(&state, lan)?;
+
+                    node.preceding_comment = pending_comment.take();
+
+                    previous = state;
+                    state.play_unchecked(lan)?;
+
+                    line.push(Entry {
+                        node,
+                        variations: Vec::new(),
+                    });
+                }
+            }
+        }
+
+        if variation {
+            return Err(ChessError(
+                ChessErrorKind::InvalidString,
+                "A variation was opened but never closed.",
+            ));
+        }
+
+        Ok((line, None))
+    }
+}
+
+/// Turns a line into the list of moves that can be played from the start of the line.
+fn fold(line: Vec<Entry>) -> Vec<Node> {
+    let mut result: Vec<Node> = Vec::new();
+
+    // Working backwards means every move's continuation is already built.
+    for entry in line.into_iter().rev() {
+        let mut node = entry.node;
+
+        node.children = result;
+
+        result = vec![node];
+
+        for variation in entry.variations {
+            result.extend(fold(variation));
+        }
+    }
+
+    result
+}
+
+/// Parses every game within the given text.
+pub fn parse(text: &str) -> Result<Vec<Game>, ChessError> {
+    let mut parser = Parser {
+        tokens: tokenize(text)?,
+        index: 0,
+    };
+
+    let mut games = Vec::new();
+
+    while parser.peek().is_some() {
+        games.push(parser.parse_game()?);
+    }
+
+    Ok(games)
+}
+
+impl TryFrom<&str> for Game {
+    type Error = ChessError;
+
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        parse(value)?.into_iter().next().ok_or(ChessError(
+            ChessErrorKind::InvalidString,
+            "The given string does not contain a game.",
+        ))
+    }
+}
+
+struct Writer {
+    tokens: Vec<String>,
+    /// Whether or not the next token should be attached to the previous one (e.g. after `(`).
+    attach: bool,
+}
+
+impl Writer {
+    fn push(&mut self, token: String) {
+        match (self.attach, self.tokens.last_mut()) {
+            (true, Some(last)) => last.push_str(&token),
+            _ => self.tokens.push(token),
+        }
+
+        self.attach = false;
+    }
+
+    fn push_comment(&mut self, comment: &str) {
+        let words = comment.split_whitespace().collect::<Vec<&str>>();
+
+        match words.len() {
+            0 => self.push(String::from("{}")),
+            _ => {
+                for (i, word) in words.iter().enumerate() {
+                    let mut token = String::from(*word);
+
+                    if i == 0 {
+                        token.insert(0, '{');
+                    }
+
+                    if i == words.len() - 1 {
+                        token.push('}');
+                    }
+
+                    self.push(token);
+                }
+            }
+        }
+    }
+
+    /// Writes a single move, and returns whether or not the following move needs its number.
+    fn push_node(&mut self, node: &Node, ply: usize, force_number: bool) -> bool {
+        let mut force_number = force_number;
+
+        if let Some(comment) = &node.preceding_comment {
+            self.push_comment(comment);
+
+            force_number = true;
+        }
+
+        let number = ply / 2 + 1;
+
+        if ply % 2 == 0 {
+            self.push(format!("{}.", number));
+        } else if force_number {
+            self.push(format!("{}...", number));
+        }
+
+        self.push(node.san.clone());
+
+        for nag in &node.nags {
+            self.push(format!("${}", nag));
+        }
+
+        if let Some(comment) = &node.comment {
+            self.push_comment(comment);
+
+            return true;
+        }
+
+        false
+    }
+
+    fn push_line(&mut self, moves: &[Node], ply: usize, force_number: bool) {
+        let (main, variations) = match moves.split_first() {
+            Some(split) => split,
+            None => return,
+        };
+
+        let force_number = self.push_node(main, ply, force_number);
+
+        for variation in variations {
+            self.push(String::from("("));
+            self.attach = true;
+
+            let force_number = self.push_node(variation, ply, true);
+
+            self.push_line(&variation.children, ply + 1, force_number);
+
+            if let Some(last) = self.tokens.last_mut() {
+                last.push(')');
+            }
+        }
+
+        self.push_line(
+            &main.children,
+            ply + 1,
+            force_number || !variations.is_empty(),
+        );
+    }
+}
+
+impl From<&Game> for String {
+    fn from(value: &Game) -> Self {
+        let mut result = String::new();
+
+        for (name, value) in &value.tags {
+            let value = value.replace('\\', "\\\\").replace('"', "\\\"");
+
+            result.push_str(&format!("[{} \"{}\"]\n", name, value));
+        }
+
+        result.push('\n');
+
+        // Plies are counted from the very beginning of the game so that move numbers line up with
+        // games that started from a custom position.
+        let ply = match value.initial_state() {
+            Ok(state) => {
+                (state.full_moves.max(1) - 1) * 2
+                    + match state.side_to_move {
+                        Color::White => 0,
+                        Color::Black => 1,
+                    }
+            }
+            Err(_) => 0,
+        };
+
+        let mut writer = Writer {
+            tokens: Vec::new(),
+            attach: false,
+        };
+
+        writer.push_line(&value.moves, ply, true);
+        writer.push(value.result.to_string());
+
+        let mut line = String::new();
+
+        for token in writer.tokens {
+            if !line.is_empty() && line.len() + 1 + token.len() > MAX_LINE_LENGTH {
+                result.push_str(&line);
+                result.push('\n');
+                line.clear();
+            }
+
+            if !line.is_empty() {
+                line.push(' ');
+            }
+
+            line.push_str(&token);
+        }
+
+        result.push_str(&line);
+        result.push('\n');
+
+        result
+    }
+}
+
+impl Display for Game {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", String::from(self))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_game_from_str() -> Result<(), ChessError> {
+        let game = Game::try_from(
+            r#"[Event "Casual \"Game\""]
+[Site "?"]
+[Result "1-0"]
+
+% This line is ignored.
+{Opening comment} 1. e4 e5 2. Nf3!? (2. Bc4 {Bishop's opening} Nf6 (2... Bc5 3. Qh5)) 2... Nc6 $1
+3. Bb5 ; The Ruy Lopez
+a6 1-0"#,
+        )?;
+
+        assert_eq!(game.tag("Event"), Some("Casual \"Game\""));
+        assert_eq!(game.tag("White"), None);
+        assert_eq!(game.result, GameResult::WhiteWins);
+
+        let mainline = ["e2e4", "e7e5", "g1f3", "b8c6", "f1b5", "a7a6"]
+            .iter()
+            .map(|&lan| Lan::try_from(lan))
+            .collect::<Result<Vec<Lan>, ChessError>>()?;
+
+        assert_eq!(game.mainline(), mainline);
+
+        let e4 = &game.moves[0];
+
+        assert_eq!(e4.preceding_comment.as_deref(), Some("Opening comment"));
+
+        let nf3 = &e4.children[0].children[0];
+        let bc4 = &e4.children[0].children[1];
+
+        assert_eq!(nf3.san, "Nf3");
+        assert_eq!(nf3.nags, vec![5]);
+        assert_eq!(nf3.children[0].nags, vec![1]);
+        assert_eq!(bc4.san, "Bc4");
+        assert_eq!(bc4.comment.as_deref(), Some("Bishop's opening"));
+        assert_eq!(bc4.children[0].san, "Nf6");
+        assert_eq!(bc4.children[1].san, "Bc5");
+        assert_eq!(bc4.children[1].children[0].san, "Qh5");
+
+        let bb5 = &nf3.children[0].children[0];
+
+        assert_eq!(bb5.comment.as_deref(), Some("The Ruy Lopez"));
+
+        // Illegal moves and unbalanced variations are rejected.
+        assert!(Game::try_from("1. e4 e4 *").is_err());
+        assert!(Game::try_from("1. e4 (1. d4 *").is_err());
+        assert!(Game::try_from("1. e4 ) *").is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_multiple_games() -> Result<(), ChessError> {
+        let games = parse(
+            r#"[Event "A"]
+
+1. f3 e5 2. g4 Qh4# 0-1
+
+[Event "B"]
+[SetUp "1"]
+[FEN "4k3/8/8/8/8/8/8/R3K3 b - - 0 40"]
+
+40... Kd7 41. Ra7+ 1/2-1/2
+"#,
+        )?;
+
+        assert_eq!(games.len(), 2);
+        assert_eq!(games[0].result, GameResult::BlackWins);
+        assert_eq!(
+            games[0].moves[0].children[0].children[0].children[0].san,
+            "Qh4#"
+        );
+        assert_eq!(games[1].tag("Event"), Some("B"));
+        assert_eq!(games[1].result, GameResult::Draw);
+        assert_eq!(games[1].mainline().len(), 2);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_game_to_string() -> Result<(), ChessError> {
+        let text = r#"[Event "?"]
+[Site "?"]
+[Date "????.??.??"]
+[Round "?"]
+[White "?"]
+[Black "?"]
+[Result "*"]
+
+{Opening comment} 1. e4 e5 2. Nf3 $5 (2. Bc4 {Bishop's opening} 2... Nf6 (2...
+Bc5 3. Qh5)) 2... Nc6 $1 3. Bb5 {The Ruy Lopez} 3... a6 *
+"#;
+
+        let game = Game::try_from(text)?;
+
+        assert_eq!(game.to_string(), text);
+
+        // Games that start from a custom position keep their move numbers.
+        let text = r#"[SetUp "1"]
+[FEN "4k3/8/8/8/8/8/8/R3K3 b - - 0 40"]
+
+40... Kd7 41. Ra7+ *
+"#;
+
+        assert_eq!(Game::try_from(text)?.to_string(), text);
+
+        // The seven tag roster is written by default.
+        let mut game = Game::default();
+        let state = State::default();
+
+        game.moves.push(Node::new(&state, Lan::try_from("d2d4")?)?);
+        game.set_tag("White", "Pescado");
+
+        assert_eq!(
+            game.to_string(),
+            "[Event \"?\"]\n[Site \"?\"]\n[Date \"????.??.??\"]\n[Round \"?\"]\n[White \"Pescado\"]\n[Black \"?\"]\n[Result \"*\"]\n\n1. d4 *\n"
+        );
+
+        Ok(())
+    }
+}
@@ -0,0 +1,554 @@
+//! Runners for suites of test positions.
+
+use crate::{
+    utils, ChessError, ChessErrorKind, Engine, Epd, Fen, GoLimits, Lan, MoveFilter, Pescado, State,
+    TimeBudget, MAX_SEARCH_DEPTH,
+};
+use std::fmt::Display;
+
+/// How long the engine is allowed to think about each position.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum SearchLimit {
+    Depth(u8),
+    Nodes(u128),
+    /// The amount of milliseconds to search for.
+    Movetime(u64),
+}
+
+/// How the engine fared against a single position.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct TacticResult {
+    pub id: Option<String>,
+    /// The move the engine suggested in Standard Algebraic Notation.
+    pub suggestion: Option<String>,
+    pub solved: bool,
+    /// The amount of milliseconds the search took.
+    pub time: u64,
+    pub nodes: u128,
+}
+
+impl From<&TacticResult> for String {
+    fn from(value: &TacticResult) -> Self {
+        format!(
+            "{} {} {} ({} ms, {} nodes)",
+            value.id.as_deref().unwrap_or("?"),
+            if value.solved { "solved" } else { "failed" },
+            value.suggestion.as_deref().unwrap_or("(none)"),
+            value.time,
+            value.nodes
+        )
+    }
+}
+
+impl Display for TacticResult {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", String::from(self))
+    }
+}
+
+#[derive(Debug, Default, PartialEq, Eq, Clone)]
+pub struct TacticsReport {
+    pub results: Vec<TacticResult>,
+}
+
+impl TacticsReport {
+    pub fn solved(&self) -> usize {
+        self.results.iter().filter(|result| result.solved).count()
+    }
+
+    pub fn total(&self) -> usize {
+        self.results.len()
+    }
+
+    /// The amount of milliseconds every search took combined.
+    pub fn time(&self) -> u64 {
+        self.results.iter().map(|result| result.time).sum()
+    }
+}
+
+impl From<&TacticsReport> for String {
+    fn from(value: &TacticsReport) -> Self {
+        format!(
+            "Solved {}/{} in {} ms",
+            value.solved(),
+            value.total(),
+            value.time()
+        )
+    }
+}
+
+impl Display for TacticsReport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", String::from(self))
+    }
+}
+
+/// Returns every EPD record in the given text, skipping blank lines and lines starting with `#`.
+fn records(text: &str) -> Result<Vec<Epd>, ChessError> {
+    text.lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(Epd::try_from)
+        .collect()
+}
+
+/// Searches every position of a tactical test suite (e.g. WAC), and checks whether or not the
+/// suggested move matches the position's `bm` operation and avoids its `am` operation.
+///
+/// `callback` is called as soon as each position is finished.
+pub fn tactics<F>(
+    text: &str,
+    limit: SearchLimit,
+    mut callback: F,
+) -> Result<TacticsReport, ChessError>
+where
+    F: FnMut(&TacticResult),
+{
+    let positions = records(text)?;
+    let mut report = TacticsReport::default();
+
+    // The engine's own output is not interesting here.
+    let mut engine = Pescado::new(|_| {});
+
+    for epd in positions {
+        let best_moves = epd.best_moves()?;
+        let avoid_moves = epd.avoid_moves()?;
+
+        if best_moves.is_empty() && avoid_moves.is_empty() {
+            return Err(ChessError(
+                ChessErrorKind::Other,
+                "Every position in a tactical test suite needs a bm or am operation.",
+            ));
+        }
+
+        let state = State::from(epd.clone());
+
+        // Every position is searched independently of the ones before it.
+        engine.new_game();
+        engine.set_position(state, Vec::new());
+
+        let start = utils::timestamp();
+
+        let (suggestion, nodes) = match limit {
+            SearchLimit::Depth(depth) => engine.go_depth(depth, Default::default()),
+            SearchLimit::Nodes(nodes) => engine.go_depth(
+                MAX_SEARCH_DEPTH,
+                GoLimits {
+                    nodes: Some(nodes),
+                    ..Default::default()
+                },
+            ),
+            SearchLimit::Movetime(movetime) => engine.go_depth(
+                MAX_SEARCH_DEPTH,
+                GoLimits {
+                    budget: Some(TimeBudget::fixed(movetime)),
+                    ..Default::default()
+                },
+            ),
+        };
+
+        let time = utils::timestamp().saturating_sub(start);
+
+        let solved = match suggestion.lan {
+            Some(lan) => {
+                (best_moves.is_empty() || best_moves.contains(&lan)) && !avoid_moves.contains(&lan)
+            }
+            None => false,
+        };
+
+        let result = TacticResult {
+            id: epd.id().map(String::from),
+            suggestion: suggestion
+                .lan
+                .map(|lan| state.san(lan).unwrap_or_else(|_| lan.to_string())),
+            solved,
+            time,
+            nodes,
+        };
+
+        callback(&result);
+
+        report.results.push(result);
+    }
+
+    Ok(report)
+}
+
+/// The outcome of verifying a single depth of a single position.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct PerftResult {
+    pub fen: String,
+    pub depth: u8,
+    pub expected: u128,
+    pub actual: u128,
+    /// The amount of milliseconds the count took.
+    pub time: u64,
+}
+
+impl PerftResult {
+    pub fn passed(&self) -> bool {
+        self.expected == self.actual
+    }
+}
+
+impl From<&PerftResult> for String {
+    fn from(value: &PerftResult) -> Self {
+        format!(
+            "{} D{} {} (expected {}, found {}, {} ms)",
+            value.fen,
+            value.depth,
+            if value.passed() { "passed" } else { "FAILED" },
+            value.expected,
+            value.actual,
+            value.time
+        )
+    }
+}
+
+impl Display for PerftResult {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", String::from(self))
+    }
+}
+
+#[derive(Debug, Default, PartialEq, Eq, Clone)]
+pub struct PerftReport {
+    pub results: Vec<PerftResult>,
+}
+
+impl PerftReport {
+    pub fn passed(&self) -> usize {
+        self.results.iter().filter(|result| result.passed()).count()
+    }
+
+    pub fn total(&self) -> usize {
+        self.results.len()
+    }
+
+    /// The amount of milliseconds every count took combined.
+    pub fn time(&self) -> u64 {
+        self.results.iter().map(|result| result.time).sum()
+    }
+}
+
+impl From<&PerftReport> for String {
+    fn from(value: &PerftReport) -> Self {
+        format!(
+            "Passed {}/{} in {} ms",
+            value.passed(),
+            value.total(),
+            value.time()
+        )
+    }
+}
+
+impl Display for PerftReport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", String::from(self))
+    }
+}
+
+/// A cache of subtree sizes keyed by a State's hash and the depth that was counted.
+struct PerftTable {
+    entries: Vec<Option<(u64, u8, u128)>>,
+}
+
+impl PerftTable {
+    fn new(megabytes: usize) -> Self {
+        let length =
+            megabytes.max(1) * 1024 * 1024 / std::mem::size_of::<Option<(u64, u8, u128)>>();
+
+        PerftTable {
+            entries: vec![None; length],
+        }
+    }
+
+    fn index(&self, hash: u64) -> usize {
+        (hash % self.entries.len() as u64) as usize
+    }
+
+    fn probe(&self, hash: u64, depth: u8) -> Option<u128> {
+        match self.entries[self.index(hash)] {
+            Some((entry_hash, entry_depth, total))
+                if entry_hash == hash && entry_depth == depth =>
+            {
+                Some(total)
+            }
+            _ => None,
+        }
+    }
+
+    fn store(&mut self, hash: u64, depth: u8, total: u128) {
+        let index = self.index(hash);
+
+        self.entries[index] = Some((hash, depth, total));
+    }
+}
+
+fn perft_hashed(state: &mut State, depth: u8, table: &mut PerftTable) -> u128 {
+    // The leaves are cheap enough that caching them is not worth it.
+    if depth <= 1 {
+        return Engine::perft(state, depth);
+    }
+
+    if let Some(total) = table.probe(state.hash, depth) {
+        return total;
+    }
+
+    let mut total = 0;
+
+    for &mv in state
+        .analyze(state.side_to_move, MoveFilter::All)
+        .moves
+        .iter()
+    {
+        let undoer = state
+            .make_move(mv)
+            .expect("The given move should always be valid.");
+
+        total += perft_hashed(state, depth - 1, table);
+
+        state.unmake_move(undoer);
+    }
+
+    table.store(state.hash, depth, total);
+
+    total
+}
+
+/// A position of a perft suite along with the expected count at each depth.
+struct PerftRecord {
+    fen: String,
+    depths: Vec<(u8, u128)>,
+}
+
+/// Returns every position of a perft suite.
+///
+/// Each line is a FEN (optionally without its move counters) followed by its expected counts
+/// (e.g. `<fen> ;D1 20 ;D2 400`).
+fn perft_records(text: &str) -> Result<Vec<PerftRecord>, ChessError> {
+    let mut records = Vec::new();
+
+    for line in text.lines().map(str::trim) {
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        let mut sections = line.split(';').map(str::trim);
+
+        let fen = sections.next().unwrap_or_default();
+        let fen = match fen.split_whitespace().count() {
+            4 => format!("{} 0 1", fen),
+            _ => String::from(fen),
+        };
+
+        Fen::try_from(fen.as_str())?;
+
+        let mut depths = Vec::new();
+
+        for section in sections.filter(|section| !section.is_empty()) {
+            let mut parts = section.trim_start_matches('D').split_whitespace();
+
+            let depth = parts.next().and_then(|depth| depth.parse::<u8>().ok());
+            let expected = parts
+                .next()
+                .and_then(|expected| expected.parse::<u128>().ok());
+
+            match (depth, expected) {
+                (Some(depth), Some(expected)) if section.starts_with('D') => {
+                    depths.push((depth, expected))
+                }
+                _ => {
+                    return Err(ChessError(
+                        ChessErrorKind::InvalidString,
+                        "Expected each count to look like \"D<depth> <count>\".",
+                    ))
+                }
+            }
+        }
+
+        records.push(PerftRecord { fen, depths });
+    }
+
+    Ok(records)
+}
+
+/// Counts the leaves of every position of a perft suite (e.g. perftsuite.epd), and checks them
+/// against the expected counts.
+///
+/// Depths greater than `max_depth` are skipped. Passing the size (in megabytes) of a hash table
+/// speeds up deep counts considerably.
+pub fn perft<F>(
+    text: &str,
+    max_depth: Option<u8>,
+    hash: Option<usize>,
+    mut callback: F,
+) -> Result<PerftReport, ChessError>
+where
+    F: FnMut(&PerftResult),
+{
+    let records = perft_records(text)?;
+    let mut report = PerftReport::default();
+    let mut table = hash.map(PerftTable::new);
+
+    for PerftRecord { fen, depths } in records {
+        let mut state = State::from(Fen::try_from(fen.as_str())?);
+
+        for (depth, expected) in depths {
+            if max_depth.map(|max| depth > max).unwrap_or(false) {
+                continue;
+            }
+
+            let start = utils::timestamp();
+
+            let actual = match &mut table {
+                Some(table) => perft_hashed(&mut state, depth, table),
+                None => Engine::perft(&mut state, depth),
+            };
+
+            let result = PerftResult {
+                fen: fen.clone(),
+                depth,
+                expected,
+                actual,
+                time: utils::timestamp().saturating_sub(start),
+            };
+
+            callback(&result);
+
+            report.results.push(result);
+        }
+    }
+
+    Ok(report)
+}
+
+/// Plays the given path of moves, and then counts the leaves of each remaining move at whatever
+/// is left of the given depth.
+///
+/// Comparing the counts against a reference engine, and extending the path with whichever move
+/// disagrees, narrows a move generation bug down to a single position.
+pub fn divide(state: &State, depth: u8, path: &[Lan]) -> Result<Vec<(Lan, u128)>, ChessError> {
+    if depth as usize <= path.len() {
+        return Err(ChessError(
+            ChessErrorKind::Other,
+            "The depth must be greater than the length of the path.",
+        ));
+    }
+
+    let mut state = *state;
+
+    Engine::make_sequence(&mut state, path)?;
+
+    let depth = depth - path.len() as u8;
+
+    let result = state
+        .analyze(state.side_to_move, MoveFilter::All)
+        .moves
+        .iter()
+        .map(|&mv| {
+            let undoer = state
+                .make_move(mv)
+                .expect("The given move should always be valid.");
+
+            let total = Engine::perft(&mut state, depth - 1);
+
+            state.unmake_move(undoer);
+
+            (Lan::from(mv), total)
+        })
+        .collect();
+
+    Ok(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tactics() -> Result<(), ChessError> {
+        let suite = r#"
+# Mate in one.
+rnbqkbnr/pppp1ppp/8/4p3/6P1/5P2/PPPPP2P/RNBQKBNR b KQkq - bm Qh4#; id "mate.001";
+
+# Taking the pawn loses the queen to the recapture.
+4k3/8/4p3/3p4/8/8/8/3QK3 w - - am Qxd5; id "avoid.001";
+"#;
+
+        let mut finished = 0;
+
+        let report = tactics(suite, SearchLimit::Depth(2), |_| finished += 1)?;
+
+        assert_eq!(finished, 2);
+        assert_eq!(report.total(), 2);
+        assert_eq!(report.solved(), 2);
+        assert_eq!(report.results[0].id.as_deref(), Some("mate.001"));
+        assert_eq!(report.results[0].suggestion.as_deref(), Some("Qh4#"));
+
+        // Positions need something to be scored against.
+        assert!(tactics("4k3/8/8/8/8/8/8/4K3 w - -", SearchLimit::Depth(1), |_| ()).is_err());
+
+        // Nothing a previous position taught the engine carries over to the next one.
+        let kiwipete = "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - bm Bxa6;";
+        let alone = tactics(kiwipete, SearchLimit::Depth(4), |_| ())?;
+        let after = tactics(
+            &format!("{}\n{}", suite, kiwipete),
+            SearchLimit::Depth(4),
+            |_| (),
+        )?;
+
+        assert_eq!(alone.results[0].nodes, after.results[2].nodes);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_perft() -> Result<(), ChessError> {
+        let suite = "
+rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1 ;D1 20 ;D2 400 ;D3 8902
+r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - ;D1 48 ;D2 2039 ;D3 97862
+8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - ;D1 14 ;D2 191 ;D3 2812 ;D4 43238
+";
+
+        let report = perft(suite, Some(3), None, |_| ())?;
+
+        assert_eq!(report.total(), 9);
+        assert_eq!(report.passed(), 9);
+
+        let report = perft(suite, None, Some(1), |_| ())?;
+
+        assert_eq!(report.total(), 10);
+        assert_eq!(report.passed(), 10);
+
+        // A wrong count is reported rather than treated as an error.
+        let report = perft("4k3/8/8/8/8/8/8/4K3 w - - ;D1 6", None, None, |_| ())?;
+
+        assert_eq!(report.passed(), 0);
+        assert_eq!(report.results[0].actual, 5);
+
+        assert!(perft("4k3/8/8/8/8/8/8/4K3 w - - ;1 5", None, None, |_| ()).is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_divide() -> Result<(), ChessError> {
+        let state = State::default();
+
+        let moves = divide(&state, 3, &[])?;
+
+        assert_eq!(moves.len(), 20);
+        assert_eq!(moves.iter().map(|(_, total)| total).sum::<u128>(), 8902);
+
+        let moves = divide(&state, 3, &[Lan::try_from("e2e4")?])?;
+
+        assert_eq!(moves.len(), 20);
+        assert_eq!(moves.iter().map(|(_, total)| total).sum::<u128>(), 600);
+
+        assert!(divide(&state, 1, &[Lan::try_from("e2e4")?]).is_err());
+        assert!(divide(&state, 3, &[Lan::try_from("e2e5")?]).is_err());
+
+        Ok(())
+    }
+}
@@ -1,4 +1,22 @@
+#[cfg(target_arch = "wasm32")]
 pub fn set_panic_hook() {
     #[cfg(feature = "console_error_panic_hook")]
     console_error_panic_hook::set_once();
 }
+
+/// Returns the amount of milliseconds that have elapsed since a fixed point in time; only the
+/// difference between two timestamps is meaningful.
+pub fn timestamp() -> u64 {
+    #[cfg(target_arch = "wasm32")]
+    return js_sys::Date::now() as u64;
+
+    // Unlike the system clock, an Instant never jumps when the wall clock is adjusted.
+    #[cfg(not(target_arch = "wasm32"))]
+    {
+        static EPOCH: std::sync::OnceLock<std::time::Instant> = std::sync::OnceLock::new();
+
+        let epoch = EPOCH.get_or_init(std::time::Instant::now);
+
+        epoch.elapsed().as_millis() as u64
+    }
+}
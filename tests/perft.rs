@@ -1,5 +1,3 @@
-use chess;
-
 // Learn more about perft here:
 // https://www.chessprogramming.org/Perft_Results
 
@@ -84,3 +82,50 @@ fn test_engine_perft_position_6() -> Result<(), chess::ChessError> {
 
     Ok(())
 }
+
+fn threads() -> usize {
+    std::thread::available_parallelism()
+        .map(usize::from)
+        .unwrap_or(1)
+}
+
+#[test]
+fn test_engine_perft_parallel_matches_perft() -> Result<(), chess::ChessError> {
+    let fen = chess::Fen::try_from(
+        "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+    )?;
+    let mut state = chess::State::from(fen);
+
+    let total_moves = chess::Engine::perft_parallel(&state, 3, threads());
+
+    assert_eq!(total_moves, chess::Engine::perft(&mut state, 3));
+    assert_eq!(total_moves, 97_862);
+
+    Ok(())
+}
+
+#[test]
+#[ignore]
+fn test_engine_perft_position_1_depth_6() -> Result<(), chess::ChessError> {
+    let fen = chess::Fen::try_from("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1")?;
+    let state = chess::State::from(fen);
+
+    let total_moves = chess::Engine::perft_parallel(&state, 6, threads());
+
+    assert_eq!(total_moves, 119_060_324);
+
+    Ok(())
+}
+
+#[test]
+#[ignore]
+fn test_engine_perft_position_3_depth_7() -> Result<(), chess::ChessError> {
+    let fen = chess::Fen::try_from("8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1")?;
+    let state = chess::State::from(fen);
+
+    let total_moves = chess::Engine::perft_parallel(&state, 7, threads());
+
+    assert_eq!(total_moves, 178_633_661);
+
+    Ok(())
+}